@@ -0,0 +1,13 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&crate_dir)
+        .expect("Unable to generate the C header")
+        .write_to_file(crate_dir.join("include/chip8.h"));
+}
@@ -0,0 +1,179 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from crates/chip8-ffi. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of every fallible call. The values are stable and will not be renumbered.
+ */
+typedef enum Chip8Status {
+  CHIP8_STATUS_OK = 0,
+  CHIP8_STATUS_NULL_POINTER = 1,
+  CHIP8_STATUS_INVALID_ARGUMENT = 2,
+  CHIP8_STATUS_ROM_TOO_LARGE = 3,
+  CHIP8_STATUS_INVALID_STATE = 4,
+  CHIP8_STATUS_BUFFER_TOO_SMALL = 5,
+  CHIP8_STATUS_INVALID_OPCODE = 16,
+  CHIP8_STATUS_MEMORY_ACCESS = 17,
+  CHIP8_STATUS_STACK_UNDERFLOW = 18,
+  CHIP8_STATUS_STACK_OVERFLOW = 19,
+} Chip8Status;
+
+/**
+ * Values accepted by `chip8_create`.
+ */
+typedef enum Chip8Target {
+  CHIP8_TARGET_COSMAC_VIP = 0,
+  CHIP8_TARGET_MODERN = 1,
+  CHIP8_TARGET_CHIP48 = 2,
+  CHIP8_TARGET_SUPER_CHIP = 3,
+  CHIP8_TARGET_XO_CHIP = 4,
+} Chip8Target;
+
+/**
+ * An emulator instance, opaque to C.
+ */
+typedef struct Chip8Handle Chip8Handle;
+
+typedef struct Chip8Registers {
+  uint8_t v[16];
+  uint16_t i;
+  uint16_t pc;
+  uint8_t sp;
+  uint8_t dt;
+  uint8_t st;
+} Chip8Registers;
+
+/**
+ * Creates an emulator for `target`, one of the `Chip8Target` values, or returns null if the
+ * target is unknown. Free it with `chip8_destroy`.
+ */
+struct Chip8Handle *chip8_create(int32_t target);
+
+/**
+ * # Safety
+ *
+ * `handle` must be null or come from `chip8_create`, and must not be used afterwards.
+ */
+void chip8_destroy(struct Chip8Handle *handle);
+
+/**
+ * Copies `size` bytes of ROM to 0x200 and starts running it on a fresh machine. An empty ROM
+ * is an invalid argument.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live, and `data` must point to `size` readable bytes.
+ */
+enum Chip8Status chip8_load_rom(struct Chip8Handle *handle, const uint8_t *data, size_t size);
+
+/**
+ * Executes one instruction.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live.
+ */
+enum Chip8Status chip8_step(struct Chip8Handle *handle);
+
+/**
+ * Executes one 60 Hz frame of instructions and ticks the timers.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live.
+ */
+enum Chip8Status chip8_run_frame(struct Chip8Handle *handle);
+
+/**
+ * Describes the error behind the last failed `chip8_step` or `chip8_run_frame`, or returns null
+ * if it succeeded. The string lives until the next call on `handle`.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live.
+ */
+const char *chip8_last_error(const struct Chip8Handle *handle);
+
+/**
+ * Presses or releases `key`, 0 to 15.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live.
+ */
+enum Chip8Status chip8_set_key(struct Chip8Handle *handle, uint8_t key, bool pressed);
+
+/**
+ * Writes the display size in pixels.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live, and `width` and `height` must be writable.
+ */
+enum Chip8Status chip8_video_size(const struct Chip8Handle *handle,
+                                  uint16_t *width,
+                                  uint16_t *height);
+
+/**
+ * Copies the display into `pixels`, one byte per pixel row by row, 1 where lit. `size` must be
+ * at least width * height from `chip8_video_size`.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live, and `pixels` must point to `size` writable bytes.
+ */
+enum Chip8Status chip8_framebuffer(const struct Chip8Handle *handle, uint8_t *pixels, size_t size);
+
+/**
+ * # Safety
+ *
+ * `handle` must be null or live, and `registers` must be writable.
+ */
+enum Chip8Status chip8_registers(const struct Chip8Handle *handle,
+                                 struct Chip8Registers *registers);
+
+/**
+ * Whether the sound timer is running, meaning the buzzer should sound.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live.
+ */
+bool chip8_sound_active(const struct Chip8Handle *handle);
+
+/**
+ * Size in bytes of a saved state.
+ */
+size_t chip8_state_size(void);
+
+/**
+ * Saves the machine into `data`, which must hold at least `chip8_state_size()` bytes.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live, and `data` must point to `size` writable bytes.
+ */
+enum Chip8Status chip8_save_state(const struct Chip8Handle *handle, uint8_t *data, size_t size);
+
+/**
+ * Restores a state written by `chip8_save_state`.
+ *
+ * # Safety
+ *
+ * `handle` must be null or live, and `data` must point to `size` readable bytes.
+ */
+enum Chip8Status chip8_load_state(struct Chip8Handle *handle, const uint8_t *data, size_t size);
+
+/**
+ * A static description of a `Chip8Status` value.
+ */
+const char *chip8_status_message(int32_t status);
+
+#endif  /* CHIP8_H */
@@ -0,0 +1,477 @@
+//! C ABI over `Chip8`. The header is generated into `include/chip8.h` on every build.
+//!
+//! Every function taking a `Chip8Handle` pointer accepts null and reports
+//! `CHIP8_STATUS_NULL_POINTER` rather than crashing.
+
+use std::ffi::{c_char, CStr, CString};
+use std::slice;
+
+use chip8::{Chip8, ExecutionError, Platform, RomError, Target, SNAPSHOT_SIZE};
+
+/// Result of every fallible call. The values are stable and will not be renumbered.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidArgument = 2,
+    RomTooLarge = 3,
+    InvalidState = 4,
+    BufferTooSmall = 5,
+    InvalidOpcode = 16,
+    MemoryAccess = 17,
+    StackUnderflow = 18,
+    StackOverflow = 19,
+}
+
+impl From<&ExecutionError> for Chip8Status {
+    fn from(err: &ExecutionError) -> Self {
+        match err {
+            ExecutionError::InvalidOpcode(_) => Self::InvalidOpcode,
+            ExecutionError::MemoryAccessError(_) => Self::MemoryAccess,
+            ExecutionError::StackUnderflow(_) => Self::StackUnderflow,
+            ExecutionError::StackOverflow(_) => Self::StackOverflow,
+        }
+    }
+}
+
+/// Values accepted by `chip8_create`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Target {
+    CosmacVip = 0,
+    Modern = 1,
+    Chip48 = 2,
+    SuperChip = 3,
+    XoChip = 4,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chip8Registers {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+}
+
+/// An emulator instance, opaque to C.
+pub struct Chip8Handle {
+    chip8: Chip8,
+    last_error: Option<CString>,
+}
+
+impl Chip8Handle {
+    fn record(&mut self, result: Result<(), ExecutionError>) -> Chip8Status {
+        match result {
+            Ok(()) => {
+                self.last_error = None;
+                Chip8Status::Ok
+            }
+            Err(err) => {
+                let status = Chip8Status::from(&err);
+                self.last_error = CString::new(err.to_string()).ok();
+                status
+            }
+        }
+    }
+}
+
+/// Creates an emulator for `target`, one of the `Chip8Target` values, or returns null if the
+/// target is unknown. Free it with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create(target: i32) -> *mut Chip8Handle {
+    let target = match target {
+        0 => Target::CosmacVIP,
+        1 => Target::Modern,
+        2 => Target::Chip48,
+        3 => Target::SuperChip,
+        4 => Target::XoChip,
+        _ => return std::ptr::null_mut(),
+    };
+
+    Box::into_raw(Box::new(Chip8Handle {
+        chip8: Chip8::new(Platform::new(target)),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+///
+/// `handle` must be null or come from `chip8_create`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(handle: *mut Chip8Handle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Copies `size` bytes of ROM to 0x200 and starts running it on a fresh machine. An empty ROM
+/// is an invalid argument.
+///
+/// # Safety
+///
+/// `handle` must be null or live, and `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    handle: *mut Chip8Handle,
+    data: *const u8,
+    size: usize,
+) -> Chip8Status {
+    let Some(handle) = handle.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    match handle
+        .chip8
+        .load_rom_bytes(slice::from_raw_parts(data, size))
+    {
+        Ok(()) => Chip8Status::Ok,
+        Err(RomError::TooLarge(_)) => Chip8Status::RomTooLarge,
+        Err(_) => Chip8Status::InvalidArgument,
+    }
+}
+
+/// Executes one instruction.
+///
+/// # Safety
+///
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(handle: *mut Chip8Handle) -> Chip8Status {
+    let Some(handle) = handle.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+
+    let result = handle.chip8.step();
+    handle.record(result)
+}
+
+/// Executes one 60 Hz frame of instructions and ticks the timers.
+///
+/// # Safety
+///
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(handle: *mut Chip8Handle) -> Chip8Status {
+    let Some(handle) = handle.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+
+    let result = handle.chip8.run_frame();
+    handle.record(result)
+}
+
+/// Describes the error behind the last failed `chip8_step` or `chip8_run_frame`, or returns null
+/// if it succeeded. The string lives until the next call on `handle`.
+///
+/// # Safety
+///
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_last_error(handle: *const Chip8Handle) -> *const c_char {
+    handle
+        .as_ref()
+        .and_then(|handle| handle.last_error.as_deref())
+        .map_or(std::ptr::null(), CStr::as_ptr)
+}
+
+/// Presses or releases `key`, 0 to 15.
+///
+/// # Safety
+///
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(
+    handle: *mut Chip8Handle,
+    key: u8,
+    pressed: bool,
+) -> Chip8Status {
+    let Some(handle) = handle.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if key > 0xF {
+        return Chip8Status::InvalidArgument;
+    }
+
+    if pressed {
+        handle.chip8.key_down(usize::from(key));
+    } else {
+        handle.chip8.key_up(usize::from(key));
+    }
+
+    Chip8Status::Ok
+}
+
+/// Writes the display size in pixels.
+///
+/// # Safety
+///
+/// `handle` must be null or live, and `width` and `height` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_video_size(
+    handle: *const Chip8Handle,
+    width: *mut u16,
+    height: *mut u16,
+) -> Chip8Status {
+    let Some(handle) = handle.as_ref() else {
+        return Chip8Status::NullPointer;
+    };
+    if width.is_null() || height.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    *width = handle.chip8.platform.video_width;
+    *height = handle.chip8.platform.video_height;
+
+    Chip8Status::Ok
+}
+
+/// Copies the display into `pixels`, one byte per pixel row by row, 1 where lit. `size` must be
+/// at least width * height from `chip8_video_size`.
+///
+/// # Safety
+///
+/// `handle` must be null or live, and `pixels` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(
+    handle: *const Chip8Handle,
+    pixels: *mut u8,
+    size: usize,
+) -> Chip8Status {
+    let Some(handle) = handle.as_ref() else {
+        return Chip8Status::NullPointer;
+    };
+    if pixels.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    let platform = &handle.chip8.platform;
+    let (width, height) = (platform.video_width, platform.video_height);
+    if size < usize::from(width) * usize::from(height) {
+        return Chip8Status::BufferTooSmall;
+    }
+
+    let pixels = slice::from_raw_parts_mut(pixels, size);
+    for y in 0..height {
+        for x in 0..width {
+            pixels[usize::from(y) * usize::from(width) + usize::from(x)] =
+                u8::from(handle.chip8.has_color(x, y));
+        }
+    }
+
+    Chip8Status::Ok
+}
+
+/// # Safety
+///
+/// `handle` must be null or live, and `registers` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_registers(
+    handle: *const Chip8Handle,
+    registers: *mut Chip8Registers,
+) -> Chip8Status {
+    let Some(handle) = handle.as_ref() else {
+        return Chip8Status::NullPointer;
+    };
+    if registers.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    let snapshot = handle.chip8.registers();
+    *registers = Chip8Registers {
+        v: snapshot.v,
+        i: snapshot.i,
+        pc: snapshot.pc,
+        sp: snapshot.sp,
+        dt: snapshot.dt,
+        st: snapshot.st,
+    };
+
+    Chip8Status::Ok
+}
+
+/// Whether the sound timer is running, meaning the buzzer should sound.
+///
+/// # Safety
+///
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(handle: *const Chip8Handle) -> bool {
+    handle
+        .as_ref()
+        .is_some_and(|handle| handle.chip8.sound_active())
+}
+
+/// Size in bytes of a saved state.
+#[no_mangle]
+pub extern "C" fn chip8_state_size() -> usize {
+    SNAPSHOT_SIZE
+}
+
+/// Saves the machine into `data`, which must hold at least `chip8_state_size()` bytes.
+///
+/// # Safety
+///
+/// `handle` must be null or live, and `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(
+    handle: *const Chip8Handle,
+    data: *mut u8,
+    size: usize,
+) -> Chip8Status {
+    let Some(handle) = handle.as_ref() else {
+        return Chip8Status::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Status::NullPointer;
+    }
+    if size < SNAPSHOT_SIZE {
+        return Chip8Status::BufferTooSmall;
+    }
+
+    let state = handle.chip8.save_state();
+    slice::from_raw_parts_mut(data, state.len()).copy_from_slice(&state);
+
+    Chip8Status::Ok
+}
+
+/// Restores a state written by `chip8_save_state`.
+///
+/// # Safety
+///
+/// `handle` must be null or live, and `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    handle: *mut Chip8Handle,
+    data: *const u8,
+    size: usize,
+) -> Chip8Status {
+    let Some(handle) = handle.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    match handle.chip8.load_state(slice::from_raw_parts(data, size)) {
+        Ok(()) => Chip8Status::Ok,
+        Err(_) => Chip8Status::InvalidState,
+    }
+}
+
+/// A static description of a `Chip8Status` value.
+#[no_mangle]
+pub extern "C" fn chip8_status_message(status: i32) -> *const c_char {
+    const STATUSES: [(Chip8Status, &CStr); 10] = [
+        (Chip8Status::Ok, c"ok"),
+        (Chip8Status::NullPointer, c"null pointer"),
+        (Chip8Status::InvalidArgument, c"invalid argument"),
+        (Chip8Status::RomTooLarge, c"ROM is too big to fit in memory"),
+        (Chip8Status::InvalidState, c"invalid saved state"),
+        (Chip8Status::BufferTooSmall, c"buffer too small"),
+        (Chip8Status::InvalidOpcode, c"invalid opcode"),
+        (Chip8Status::MemoryAccess, c"memory access error"),
+        (Chip8Status::StackUnderflow, c"stack underflow"),
+        (Chip8Status::StackOverflow, c"stack overflow"),
+    ];
+
+    STATUSES
+        .iter()
+        .find(|(known, _)| *known as i32 == status)
+        .map_or(c"unknown status", |(_, message)| message)
+        .as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let rom = include_bytes!("../../../roms/PONG");
+
+        unsafe {
+            let handle = chip8_create(Chip8Target::CosmacVip as i32);
+            assert_eq!(
+                Chip8Status::Ok,
+                chip8_load_rom(handle, rom.as_ptr(), rom.len())
+            );
+            assert_eq!(Chip8Status::Ok, chip8_set_key(handle, 1, true));
+            for _ in 0..30 {
+                assert_eq!(Chip8Status::Ok, chip8_run_frame(handle));
+            }
+
+            let (mut width, mut height) = (0, 0);
+            assert_eq!(
+                Chip8Status::Ok,
+                chip8_video_size(handle, &mut width, &mut height)
+            );
+            let mut pixels = vec![0; usize::from(width) * usize::from(height)];
+            assert_eq!(
+                Chip8Status::BufferTooSmall,
+                chip8_framebuffer(handle, pixels.as_mut_ptr(), 10)
+            );
+            assert_eq!(
+                Chip8Status::Ok,
+                chip8_framebuffer(handle, pixels.as_mut_ptr(), pixels.len())
+            );
+            assert!(pixels.contains(&1));
+
+            let mut state = vec![0; chip8_state_size()];
+            assert_eq!(
+                Chip8Status::Ok,
+                chip8_save_state(handle, state.as_mut_ptr(), state.len())
+            );
+            let mut before = Chip8Registers::default();
+            chip8_registers(handle, &mut before);
+            chip8_run_frame(handle);
+            assert_eq!(
+                Chip8Status::Ok,
+                chip8_load_state(handle, state.as_ptr(), state.len())
+            );
+            let mut after = Chip8Registers::default();
+            chip8_registers(handle, &mut after);
+            assert_eq!(before.pc, after.pc);
+            assert_eq!(
+                Chip8Status::InvalidState,
+                chip8_load_state(handle, state.as_ptr(), 4)
+            );
+
+            chip8_destroy(handle);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        unsafe {
+            assert!(chip8_create(9).is_null());
+            assert_eq!(Chip8Status::NullPointer, chip8_step(std::ptr::null_mut()));
+
+            let handle = chip8_create(Chip8Target::Modern as i32);
+            assert_eq!(
+                Chip8Status::InvalidArgument,
+                chip8_set_key(handle, 16, true)
+            );
+
+            let rom = [0xFF, 0xFF];
+            chip8_load_rom(handle, rom.as_ptr(), rom.len());
+            assert_eq!(Chip8Status::InvalidOpcode, chip8_step(handle));
+            assert_eq!(
+                c"Invalid opcode 0xffff",
+                CStr::from_ptr(chip8_last_error(handle))
+            );
+            assert_eq!(
+                c"invalid opcode",
+                CStr::from_ptr(chip8_status_message(Chip8Status::InvalidOpcode as i32))
+            );
+
+            chip8_destroy(handle);
+        }
+    }
+}
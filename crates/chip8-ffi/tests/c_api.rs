@@ -0,0 +1,46 @@
+//! Compiles `smoke.c` against the generated header and the static library, then runs it.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn static_library() -> PathBuf {
+    let name = format!(
+        "{}chip8_ffi{}",
+        std::env::consts::DLL_PREFIX,
+        if cfg!(windows) { ".lib" } else { ".a" }
+    );
+    let deps = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the static library is built before the integration tests")
+}
+
+#[test]
+#[cfg(unix)]
+fn test_smoke() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let binary = std::env::temp_dir().join(format!("chip8_ffi_smoke_{}", std::process::id()));
+
+    let status = Command::new(std::env::var("CC").unwrap_or("cc".to_string()))
+        .arg(crate_dir.join("tests/smoke.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg(static_library())
+        .args(["-lpthread", "-ldl", "-lm"])
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .expect("a C compiler is installed");
+    assert!(status.success());
+
+    let output = Command::new(&binary).output().unwrap();
+    std::fs::remove_file(&binary).ok();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(b"ok\n", output.stdout.as_slice());
+}
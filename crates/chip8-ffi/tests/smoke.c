@@ -0,0 +1,33 @@
+#include <stdio.h>
+#include <string.h>
+
+#include "chip8.h"
+
+int main(void) {
+    /* 6005: LD V0, 5; F018: LD ST, V0; 1204: JP 0x204 */
+    const uint8_t rom[] = {0x60, 0x05, 0xF0, 0x18, 0x12, 0x04};
+    Chip8Handle *chip8 = chip8_create(CHIP8_TARGET_COSMAC_VIP);
+    if (chip8 == NULL) {
+        return 1;
+    }
+
+    if (chip8_load_rom(chip8, rom, sizeof rom) != CHIP8_STATUS_OK
+        || chip8_run_frame(chip8) != CHIP8_STATUS_OK) {
+        return 2;
+    }
+
+    Chip8Registers registers;
+    chip8_registers(chip8, &registers);
+    if (registers.v[0] != 5 || registers.pc != 0x204 || !chip8_sound_active(chip8)) {
+        return 3;
+    }
+
+    if (chip8_set_key(chip8, 16, true) != CHIP8_STATUS_INVALID_ARGUMENT
+        || strcmp(chip8_status_message(CHIP8_STATUS_INVALID_ARGUMENT), "invalid argument") != 0) {
+        return 4;
+    }
+
+    chip8_destroy(chip8);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,437 @@
+//! Python bindings for the `chip8` crate, built as the `chip8` extension module.
+
+use std::ffi::{c_int, c_void, CString};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyBufferError, PyIndexError, PyRuntimeError, PyValueError};
+use pyo3::ffi;
+use pyo3::prelude::*;
+
+create_exception!(
+    chip8,
+    ExecutionError,
+    PyRuntimeError,
+    "The program hit an invalid instruction or memory access."
+);
+
+fn execution_error(err: chip8::ExecutionError) -> PyErr {
+    ExecutionError::new_err(err.to_string())
+}
+
+/// The machine being emulated, which picks the default display size, speed and quirks.
+#[pyclass(eq, eq_int, module = "chip8")]
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    #[pyo3(name = "COSMAC_VIP")]
+    CosmacVIP,
+    #[pyo3(name = "MODERN")]
+    Modern,
+    #[pyo3(name = "CHIP_48")]
+    Chip48,
+    #[pyo3(name = "SUPER_CHIP")]
+    SuperChip,
+    #[pyo3(name = "XO_CHIP")]
+    XoChip,
+}
+
+impl From<Target> for chip8::Target {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::CosmacVIP => chip8::Target::CosmacVIP,
+            Target::Modern => chip8::Target::Modern,
+            Target::Chip48 => chip8::Target::Chip48,
+            Target::SuperChip => chip8::Target::SuperChip,
+            Target::XoChip => chip8::Target::XoChip,
+        }
+    }
+}
+
+impl From<chip8::Target> for Target {
+    fn from(target: chip8::Target) -> Self {
+        match target {
+            chip8::Target::CosmacVIP => Target::CosmacVIP,
+            chip8::Target::Modern => Target::Modern,
+            chip8::Target::Chip48 => Target::Chip48,
+            chip8::Target::SuperChip => Target::SuperChip,
+            chip8::Target::XoChip => Target::XoChip,
+        }
+    }
+}
+
+#[pymethods]
+impl Target {
+    /// Looks a target up by its command line name, e.g. `"super-chip"`.
+    #[staticmethod]
+    fn from_name(name: &str) -> PyResult<Self> {
+        name.parse::<chip8::Target>()
+            .map(Target::from)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[getter]
+    fn name(&self) -> &'static str {
+        chip8::Target::from(*self).name()
+    }
+}
+
+fn quirk(name: &str) -> PyResult<chip8::Quirks> {
+    chip8::Quirks::from_name(&name.to_ascii_uppercase())
+        .ok_or_else(|| PyValueError::new_err(format!("Unknown quirk {name:?}")))
+}
+
+/// Display size, speed and quirks of the emulated machine.
+#[pyclass(module = "chip8")]
+#[derive(Clone)]
+struct Platform(chip8::Platform);
+
+#[pymethods]
+impl Platform {
+    #[new]
+    #[pyo3(signature = (target = Target::Modern))]
+    fn new(target: Target) -> Self {
+        Self(chip8::Platform::new(target.into()))
+    }
+
+    #[getter]
+    fn target(&self) -> Target {
+        self.0.target.into()
+    }
+
+    #[getter]
+    fn width(&self) -> u16 {
+        self.0.video_width
+    }
+
+    #[getter]
+    fn height(&self) -> u16 {
+        self.0.video_height
+    }
+
+    /// Instructions run per 60 Hz frame.
+    #[getter]
+    fn tick_rate(&self) -> u16 {
+        self.0.tick_rate
+    }
+
+    #[setter]
+    fn set_tick_rate(&mut self, tick_rate: u16) {
+        self.0.tick_rate = tick_rate;
+    }
+
+    /// Names of the enabled quirks, e.g. `["vf_reset", "vblank"]`.
+    #[getter]
+    fn quirks(&self) -> Vec<String> {
+        self.0
+            .quirks
+            .iter_names()
+            .map(|(name, _)| name.to_ascii_lowercase())
+            .collect()
+    }
+
+    fn has_quirk(&self, name: &str) -> PyResult<bool> {
+        Ok(self.0.has_quirk(quirk(name)?))
+    }
+
+    fn set_quirk(&mut self, name: &str, enabled: bool) -> PyResult<()> {
+        self.0.quirks.set(quirk(name)?, enabled);
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Platform(target={}, width={}, height={}, tick_rate={}, quirks={:?})",
+            self.0.target,
+            self.0.video_width,
+            self.0.video_height,
+            self.0.tick_rate,
+            self.quirks()
+        )
+    }
+}
+
+#[derive(FromPyObject)]
+enum PlatformArg {
+    Target(Target),
+    Platform(Platform),
+}
+
+/// The CPU registers and timers at the time they were read.
+#[pyclass(get_all, frozen, module = "chip8")]
+struct Registers {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    sp: u8,
+    dt: u8,
+    st: u8,
+}
+
+/// A copy of the display exposing the buffer protocol as a `(height, width)` array of `uint8`,
+/// so `numpy.asarray(framebuffer)` and `memoryview(framebuffer)` work without copying again.
+#[pyclass(frozen, module = "chip8")]
+struct Framebuffer {
+    pixels: Vec<u8>,
+    shape: [ffi::Py_ssize_t; 2],
+    strides: [ffi::Py_ssize_t; 2],
+}
+
+#[pymethods]
+impl Framebuffer {
+    #[getter]
+    fn width(&self) -> ffi::Py_ssize_t {
+        self.shape[1]
+    }
+
+    #[getter]
+    fn height(&self) -> ffi::Py_ssize_t {
+        self.shape[0]
+    }
+
+    fn __len__(&self) -> usize {
+        self.pixels.len()
+    }
+
+    fn __bytes__(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// # Safety
+    ///
+    /// `view` must be null or point to a `Py_buffer` for Python to fill in.
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("Framebuffer is read-only"));
+        }
+
+        // The pixels, shape and strides live as long as the object the view keeps a reference to.
+        let framebuffer = slf.get();
+        let view = &mut *view;
+        view.buf = framebuffer.pixels.as_ptr() as *mut c_void;
+        view.len = framebuffer.pixels.len() as ffi::Py_ssize_t;
+        view.readonly = 1;
+        view.itemsize = 1;
+        view.format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            CString::from(c"B").into_raw()
+        } else {
+            std::ptr::null_mut()
+        };
+        view.ndim = 2;
+        view.shape = if flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+            framebuffer.shape.as_ptr() as *mut ffi::Py_ssize_t
+        } else {
+            std::ptr::null_mut()
+        };
+        view.strides = if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+            framebuffer.strides.as_ptr() as *mut ffi::Py_ssize_t
+        } else {
+            std::ptr::null_mut()
+        };
+        view.suboffsets = std::ptr::null_mut();
+        view.internal = std::ptr::null_mut();
+        view.obj = slf.into_any().into_ptr();
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// `view` must have been filled in by `__getbuffer__`.
+    unsafe fn __releasebuffer__(&self, view: *mut ffi::Py_buffer) {
+        if !(*view).format.is_null() {
+            drop(CString::from_raw((*view).format));
+        }
+    }
+}
+
+/// A CHIP-8 machine driven one instruction or one 60 Hz frame at a time.
+#[pyclass(module = "chip8")]
+struct Chip8(chip8::Chip8);
+
+impl Chip8 {
+    fn key(key: usize) -> PyResult<usize> {
+        if key < 16 {
+            Ok(key)
+        } else {
+            Err(PyIndexError::new_err(format!(
+                "Key {key} is out of range 0..16"
+            )))
+        }
+    }
+
+    fn address(&self, address: usize) -> PyResult<usize> {
+        if address < self.0.ram().len() {
+            Ok(address)
+        } else {
+            Err(PyIndexError::new_err(format!(
+                "Address {address:#06x} is out of range"
+            )))
+        }
+    }
+}
+
+#[pymethods]
+impl Chip8 {
+    /// Creates a machine for a `Target` or a customised `Platform`.
+    #[new]
+    #[pyo3(signature = (platform = PlatformArg::Target(Target::Modern)))]
+    fn new(platform: PlatformArg) -> Self {
+        let platform = match platform {
+            PlatformArg::Target(target) => chip8::Platform::new(target.into()),
+            PlatformArg::Platform(platform) => platform.0,
+        };
+        Self(chip8::Chip8::new(platform))
+    }
+
+    fn load_rom(&mut self, rom: &[u8]) -> PyResult<()> {
+        self.0
+            .load_rom_bytes(rom)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn reset(&mut self) -> PyResult<()> {
+        self.0.reset().map_err(execution_error)
+    }
+
+    fn step(&mut self) -> PyResult<()> {
+        self.0.step().map_err(execution_error)
+    }
+
+    /// Runs `frames` 60 Hz frames, each `platform.tick_rate` instructions and one timer tick.
+    #[pyo3(signature = (frames = 1))]
+    fn run_frame(&mut self, frames: usize) -> PyResult<()> {
+        for _ in 0..frames {
+            self.0.run_frame().map_err(execution_error)?;
+        }
+        Ok(())
+    }
+
+    fn key_down(&mut self, key: usize) -> PyResult<()> {
+        self.0.key_down(Self::key(key)?);
+        Ok(())
+    }
+
+    fn key_up(&mut self, key: usize) -> PyResult<()> {
+        self.0.key_up(Self::key(key)?);
+        Ok(())
+    }
+
+    /// Sets every key at once from 16 booleans indexed by key value.
+    fn set_keys(&mut self, keys: Vec<bool>) -> PyResult<()> {
+        if keys.len() != 16 {
+            return Err(PyValueError::new_err(format!(
+                "Expected 16 keys, got {}",
+                keys.len()
+            )));
+        }
+
+        self.0.reset_keys();
+        for (key, _) in keys.iter().enumerate().filter(|(_, &down)| down) {
+            self.0.key_down(key);
+        }
+        Ok(())
+    }
+
+    fn framebuffer(&self) -> Framebuffer {
+        let width = self.0.platform.video_width as ffi::Py_ssize_t;
+        let height = self.0.platform.video_height as ffi::Py_ssize_t;
+        Framebuffer {
+            pixels: self.0.framebuffer().to_vec(),
+            shape: [height, width],
+            strides: [width, 1],
+        }
+    }
+
+    fn peek(&self, address: usize) -> PyResult<u8> {
+        Ok(self.0.ram()[self.address(address)?])
+    }
+
+    fn poke(&mut self, address: usize, value: u8) -> PyResult<()> {
+        let address = self.address(address)?;
+        self.0.ram_mut()[address] = value;
+        Ok(())
+    }
+
+    /// A copy of the whole 4 KiB of RAM.
+    #[getter]
+    fn ram(&self) -> Vec<u8> {
+        self.0.ram().to_vec()
+    }
+
+    #[getter]
+    fn registers(&self) -> Registers {
+        let registers = self.0.registers();
+        Registers {
+            v: registers.v,
+            i: registers.i,
+            pc: registers.pc,
+            sp: registers.sp,
+            dt: registers.dt,
+            st: registers.st,
+        }
+    }
+
+    /// One of `"running"`, `"paused"`, `"finished"` or `"off"`.
+    #[getter]
+    fn state(&self) -> &'static str {
+        match self.0.state {
+            chip8::State::Running => "running",
+            chip8::State::Paused => "paused",
+            chip8::State::Finished => "finished",
+            chip8::State::Off => "off",
+        }
+    }
+
+    /// Why the program stopped: `"exit"`, `"self-jump"` or `"waiting-for-key"`, or `None`.
+    #[getter]
+    fn halt(&self) -> Option<&'static str> {
+        self.0.halt().map(|halt| match halt {
+            chip8::Halt::Exit => "exit",
+            chip8::Halt::SelfJump(_) => "self-jump",
+            chip8::Halt::WaitingForKey(_) => "waiting-for-key",
+        })
+    }
+
+    #[getter]
+    fn platform(&self) -> Platform {
+        Platform(self.0.platform.clone())
+    }
+
+    #[setter]
+    fn set_platform(&mut self, platform: Platform) {
+        self.0.platform = platform.0;
+    }
+
+    #[getter]
+    fn sound_active(&self) -> bool {
+        self.0.sound_active()
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        self.0.save_state()
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.0
+            .load_state(state)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "chip8")]
+fn init(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Chip8>()?;
+    module.add_class::<Framebuffer>()?;
+    module.add_class::<Platform>()?;
+    module.add_class::<Registers>()?;
+    module.add_class::<Target>()?;
+    module.add("ExecutionError", module.py().get_type::<ExecutionError>())?;
+    Ok(())
+}
@@ -0,0 +1,47 @@
+//! Imports the built extension module into Python and runs `test_chip8.py` against it.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn module_path() -> PathBuf {
+    let name = format!(
+        "{}chip8_python{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    );
+    let deps = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the module is built before its integration tests")
+}
+
+#[test]
+fn test_python() {
+    let python = std::env::var("PYTHON").unwrap_or_else(|_| "python3".to_string());
+    if Command::new(&python).arg("--version").output().is_err() {
+        eprintln!("Skipping: {python} is not installed");
+        return;
+    }
+
+    // Python only imports `chip8` from a file named after the module.
+    let dir = std::env::temp_dir().join(format!("chip8-python-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let suffix = if cfg!(windows) { "pyd" } else { "so" };
+    std::fs::copy(module_path(), dir.join(format!("chip8.{suffix}"))).unwrap();
+
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_chip8.py");
+    let status = Command::new(&python)
+        .arg(script)
+        .env("PYTHONPATH", &dir)
+        .status()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(status.success());
+}
@@ -0,0 +1,92 @@
+"""Exercises the extension module the way a Python script or notebook would."""
+
+import pathlib
+import unittest
+
+import chip8
+
+ROMS = pathlib.Path(__file__).resolve().parents[3] / "roms"
+
+
+class Chip8Test(unittest.TestCase):
+    def setUp(self):
+        self.chip8 = chip8.Chip8(chip8.Target.MODERN)
+        self.chip8.load_rom((ROMS / "PONG").read_bytes())
+
+    def test_run_frames(self):
+        self.assertEqual("running", self.chip8.state)
+        self.chip8.run_frame(60)
+        framebuffer = self.chip8.framebuffer()
+        self.assertEqual((64, 32), (framebuffer.width, framebuffer.height))
+        self.assertTrue(any(bytes(framebuffer)))
+
+    def test_framebuffer_buffer(self):
+        self.chip8.run_frame(10)
+        view = memoryview(self.chip8.framebuffer())
+        self.assertEqual((32, 64), view.shape)
+        self.assertEqual("B", view.format)
+        self.assertTrue(view.readonly)
+        self.assertEqual(bytes(self.chip8.framebuffer()), view.tobytes())
+
+    def test_peek_poke(self):
+        self.assertEqual(self.chip8.ram[0x200], self.chip8.peek(0x200))
+        self.chip8.poke(0x300, 0xAB)
+        self.assertEqual(0xAB, self.chip8.peek(0x300))
+        self.assertEqual(4096, len(self.chip8.ram))
+        with self.assertRaises(IndexError):
+            self.chip8.peek(0x1000)
+
+    def test_keys(self):
+        self.chip8.key_down(0xF)
+        self.chip8.key_up(0xF)
+        self.chip8.set_keys([False] * 16)
+        with self.assertRaises(IndexError):
+            self.chip8.key_down(16)
+        with self.assertRaises(ValueError):
+            self.chip8.set_keys([True])
+
+    def test_registers(self):
+        self.assertEqual(0x200, self.chip8.registers.pc)
+        self.chip8.step()
+        self.assertNotEqual(0x200, self.chip8.registers.pc)
+        self.assertEqual(16, len(self.chip8.registers.v))
+
+    def test_save_state(self):
+        self.chip8.run_frame(5)
+        state = self.chip8.save_state()
+        self.chip8.run_frame(5)
+        self.chip8.load_state(state)
+        self.assertEqual(state, self.chip8.save_state())
+        with self.assertRaises(ValueError):
+            self.chip8.load_state(b"")
+
+    def test_execution_error(self):
+        machine = chip8.Chip8()
+        machine.load_rom(bytes([0xFF, 0xFF]))
+        with self.assertRaises(chip8.ExecutionError):
+            machine.step()
+
+    def test_halt(self):
+        machine = chip8.Chip8()
+        machine.load_rom(bytes([0x12, 0x00]))
+        self.assertIsNone(machine.halt)
+        machine.step()
+        self.assertEqual("finished", machine.state)
+        self.assertEqual("self-jump", machine.halt)
+
+    def test_platform(self):
+        platform = chip8.Platform(chip8.Target.from_name("super-chip"))
+        self.assertEqual((128, 64), (platform.width, platform.height))
+        self.assertTrue(platform.has_quirk("load_store_inc_i"))
+        platform.set_quirk("wrap", True)
+        platform.tick_rate = 7
+        machine = chip8.Chip8(platform)
+        self.assertEqual(chip8.Target.SUPER_CHIP, machine.platform.target)
+        self.assertEqual(7, machine.platform.tick_rate)
+        self.assertIn("wrap", machine.platform.quirks)
+        with self.assertRaises(ValueError):
+            platform.has_quirk("nonsense")
+
+
+if __name__ == "__main__":
+    unittest.main()
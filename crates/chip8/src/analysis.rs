@@ -0,0 +1,426 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+use crate::instruction::Instruction;
+use crate::PROGRAM_START_ADDRESS;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Skip,
+    Call,
+    Indirect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: u16,
+    pub to: u16,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataKind {
+    /// Referenced by ANNN and drawn with DXYN, with the largest row count seen.
+    Sprite(u16),
+    /// Referenced by ANNN but only used for loads, stores or BCD.
+    Data,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub start: u16,
+    /// Address of the last instruction in the block.
+    pub last: u16,
+    pub instructions: Vec<(u16, Instruction)>,
+}
+
+/// Static control-flow analysis of a ROM, following every path reachable from the program start
+/// to separate code from data.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub entry: u16,
+    pub end: u16,
+    pub instructions: BTreeMap<u16, Instruction>,
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub edges: Vec<Edge>,
+    pub subroutines: BTreeSet<u16>,
+    pub jump_targets: BTreeSet<u16>,
+    /// Addresses of BNNN instructions, whose targets depend on V0 and cannot be followed.
+    pub indirect_jumps: BTreeSet<u16>,
+    pub data: BTreeMap<u16, DataKind>,
+    /// Reachable addresses holding something that does not decode as an instruction.
+    pub invalid: BTreeSet<u16>,
+    ram: Vec<u8>,
+}
+
+impl Analysis {
+    pub fn new(rom: &[u8]) -> Self {
+        let mut ram = vec![0; 4096];
+        let len = rom.len().min(ram.len() - PROGRAM_START_ADDRESS as usize);
+        let start = PROGRAM_START_ADDRESS as usize;
+        ram[start..start + len].copy_from_slice(&rom[..len]);
+
+        let mut analysis = Self {
+            entry: PROGRAM_START_ADDRESS,
+            end: PROGRAM_START_ADDRESS + len as u16,
+            instructions: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            edges: Vec::new(),
+            subroutines: BTreeSet::new(),
+            jump_targets: BTreeSet::new(),
+            indirect_jumps: BTreeSet::new(),
+            data: BTreeMap::new(),
+            invalid: BTreeSet::new(),
+            ram,
+        };
+
+        analysis.trace();
+        analysis.split_blocks();
+        analysis.find_data();
+
+        analysis
+    }
+
+    pub fn opcode(&self, addr: u16) -> u16 {
+        let hi = self.ram.get(addr as usize).copied().unwrap_or(0);
+        let lo = self.ram.get(addr as usize + 1).copied().unwrap_or(0);
+        u16::from(hi) << 8 | u16::from(lo)
+    }
+
+    pub fn byte(&self, addr: u16) -> u8 {
+        self.ram.get(addr as usize).copied().unwrap_or(0)
+    }
+
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.instructions.contains_key(&addr)
+            || addr > 0 && self.instructions.contains_key(&(addr - 1))
+    }
+
+    pub fn label(&self, addr: u16) -> Option<String> {
+        if addr == self.entry {
+            Some("main".to_string())
+        } else if self.subroutines.contains(&addr) {
+            Some(format!("sub_{addr:03x}"))
+        } else if self.jump_targets.contains(&addr) {
+            Some(format!("label_{addr:03x}"))
+        } else {
+            match self.data.get(&addr) {
+                Some(DataKind::Sprite(_)) => Some(format!("sprite_{addr:03x}")),
+                Some(DataKind::Data) => Some(format!("data_{addr:03x}")),
+                None => None,
+            }
+        }
+    }
+
+    /// Disassembles reachable code and dumps everything else in the ROM as data bytes.
+    pub fn disassembly(&self) -> String {
+        let mut listing = String::new();
+        let mut addr = self.entry;
+
+        while addr < self.end {
+            if let Some(label) = self.label(addr) {
+                writeln!(listing, "{label}:").unwrap();
+            }
+
+            if let Some(instruction) = self.instructions.get(&addr) {
+                writeln!(
+                    listing,
+                    "  {:#06x}  {:04X}  {}",
+                    addr,
+                    self.opcode(addr),
+                    instruction
+                )
+                .unwrap();
+                addr += 2;
+            } else {
+                writeln!(
+                    listing,
+                    "  {:#06x}  {:02X}    DB {:#04x}",
+                    addr,
+                    self.byte(addr),
+                    self.byte(addr)
+                )
+                .unwrap();
+                addr += 1;
+            }
+        }
+
+        listing
+    }
+
+    /// Exports the control-flow graph as a Graphviz DOT digraph of basic blocks.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        writeln!(dot, "digraph rom {{").unwrap();
+        writeln!(dot, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks.values() {
+            let mut text = String::new();
+            if let Some(label) = self.label(block.start) {
+                write!(text, "{label}:\\l").unwrap();
+            }
+            for (addr, instruction) in &block.instructions {
+                write!(text, "{addr:#05x}  {instruction}\\l").unwrap();
+            }
+
+            let style = if self.indirect_jumps.contains(&block.last) {
+                ", color=red"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "  \"{:#05x}\" [label=\"{}\"{}];",
+                block.start, text, style
+            )
+            .unwrap();
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [label=\"jump\"]",
+                EdgeKind::Skip => " [label=\"skip\"]",
+                EdgeKind::Call => " [label=\"call\", style=dashed]",
+                EdgeKind::Indirect => " [label=\"V0 +\", style=dotted, color=red]",
+            };
+            writeln!(
+                dot,
+                "  \"{:#05x}\" -> \"{:#05x}\"{};",
+                edge.from, edge.to, style
+            )
+            .unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+
+    fn successors(addr: u16, instruction: &Instruction) -> Vec<(u16, EdgeKind)> {
+        match *instruction {
+            Instruction::Ret | Instruction::Exit => vec![],
+            Instruction::Jp(nnn) => vec![(nnn, EdgeKind::Jump)],
+            Instruction::Call(nnn) => {
+                vec![(nnn, EdgeKind::Call), (addr + 2, EdgeKind::Fallthrough)]
+            }
+            Instruction::JpV0(nnn) => vec![(nnn, EdgeKind::Indirect)],
+            ref skip if skip.is_skip() => {
+                vec![
+                    (addr + 2, EdgeKind::Fallthrough),
+                    (addr + 4, EdgeKind::Skip),
+                ]
+            }
+            _ => vec![(addr + 2, EdgeKind::Fallthrough)],
+        }
+    }
+
+    fn trace(&mut self) {
+        let mut pending = vec![self.entry];
+
+        while let Some(addr) = pending.pop() {
+            if self.instructions.contains_key(&addr) || self.invalid.contains(&addr) {
+                continue;
+            }
+
+            if addr as usize + 1 >= self.ram.len() {
+                self.invalid.insert(addr);
+                continue;
+            }
+
+            let Ok(instruction) = Instruction::decode(self.opcode(addr)) else {
+                self.invalid.insert(addr);
+                continue;
+            };
+
+            self.instructions.insert(addr, instruction);
+
+            match instruction {
+                Instruction::Call(nnn) => {
+                    self.subroutines.insert(nnn);
+                }
+                Instruction::Jp(nnn) => {
+                    self.jump_targets.insert(nnn);
+                }
+                Instruction::JpV0(nnn) => {
+                    self.indirect_jumps.insert(addr);
+                    self.jump_targets.insert(nnn);
+                }
+                _ => {}
+            }
+
+            for (to, _) in Self::successors(addr, &instruction) {
+                pending.push(to);
+            }
+        }
+    }
+
+    fn split_blocks(&mut self) {
+        let mut leaders: BTreeSet<u16> = BTreeSet::new();
+        leaders.insert(self.entry);
+        leaders.extend(self.subroutines.iter().copied());
+        leaders.extend(self.jump_targets.iter().copied());
+
+        for (&addr, instruction) in &self.instructions {
+            let successors = Self::successors(addr, instruction);
+            if successors.len() != 1 || successors[0].1 != EdgeKind::Fallthrough {
+                leaders.extend(successors.iter().map(|&(to, _)| to));
+            }
+        }
+
+        for &leader in &leaders {
+            if !self.instructions.contains_key(&leader) {
+                continue;
+            }
+
+            let mut block = BasicBlock {
+                start: leader,
+                last: leader,
+                instructions: Vec::new(),
+            };
+            let mut addr = leader;
+
+            while let Some(instruction) = self.instructions.get(&addr) {
+                block.instructions.push((addr, *instruction));
+                block.last = addr;
+
+                let successors = Self::successors(addr, instruction);
+                let falls_through =
+                    successors.len() == 1 && successors[0].1 == EdgeKind::Fallthrough;
+                if !falls_through || leaders.contains(&(addr + 2)) {
+                    self.edges
+                        .extend(successors.into_iter().filter_map(|(to, kind)| {
+                            self.instructions.contains_key(&to).then_some(Edge {
+                                from: leader,
+                                to,
+                                kind,
+                            })
+                        }));
+                    break;
+                }
+
+                addr += 2;
+            }
+
+            self.blocks.insert(leader, block);
+        }
+    }
+
+    fn find_data(&mut self) {
+        for block in self.blocks.values() {
+            let mut pointer = None;
+
+            for (_, instruction) in &block.instructions {
+                match *instruction {
+                    Instruction::LdI(nnn) if !self.is_code(nnn) => {
+                        self.data.entry(nnn).or_insert(DataKind::Data);
+                        pointer = Some(nnn);
+                    }
+                    Instruction::LdI(_) | Instruction::AddI(_) | Instruction::LdF(_) => {
+                        pointer = None;
+                    }
+                    Instruction::Drw(_, _, n) => {
+                        if let Some(addr) = pointer {
+                            let rows = match self.data.get(&addr) {
+                                Some(DataKind::Sprite(rows)) => (*rows).max(n),
+                                _ => n,
+                            };
+                            self.data.insert(addr, DataKind::Sprite(rows));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0x200: CALL 0x20A
+    // 0x202: SE V0, 0x01
+    // 0x204: JP 0x202
+    // 0x206: JP V0, 0x20E
+    // 0x208: 0xFF 0xFF (unreachable data)
+    // 0x20A: LD I, 0x212
+    // 0x20C: DRW V0, V1, 2
+    // 0x20E: RET
+    // 0x210: JP 0x210
+    // 0x212: sprite
+    const ROM: [u8; 20] = [
+        0x22, 0x0A, 0x30, 0x01, 0x12, 0x02, 0xB2, 0x0E, 0xFF, 0xFF, 0xA2, 0x12, 0xD0, 0x12, 0x00,
+        0xEE, 0x12, 0x10, 0xF0, 0x90,
+    ];
+
+    #[test]
+    fn test_reachability() {
+        let analysis = Analysis::new(&ROM);
+
+        assert!(analysis.instructions.contains_key(&0x20A));
+        assert!(analysis.instructions.contains_key(&0x206));
+        assert!(!analysis.instructions.contains_key(&0x208));
+        assert!(!analysis.instructions.contains_key(&0x210));
+        assert!(!analysis.is_code(0x212));
+    }
+
+    #[test]
+    fn test_labels() {
+        let analysis = Analysis::new(&ROM);
+
+        assert_eq!(BTreeSet::from([0x20A]), analysis.subroutines);
+        assert_eq!(BTreeSet::from([0x206]), analysis.indirect_jumps);
+        assert_eq!(Some(&DataKind::Sprite(2)), analysis.data.get(&0x212));
+        assert_eq!(Some("main".to_string()), analysis.label(0x200));
+        assert_eq!(Some("sub_20a".to_string()), analysis.label(0x20A));
+        assert_eq!(Some("sprite_212".to_string()), analysis.label(0x212));
+    }
+
+    #[test]
+    fn test_blocks() {
+        let analysis = Analysis::new(&ROM);
+
+        assert_eq!(
+            vec![0x200, 0x202, 0x204, 0x206, 0x20A, 0x20E],
+            analysis.blocks.keys().copied().collect::<Vec<_>>()
+        );
+        assert!(analysis.edges.contains(&Edge {
+            from: 0x202,
+            to: 0x206,
+            kind: EdgeKind::Skip
+        }));
+        assert!(analysis.edges.contains(&Edge {
+            from: 0x204,
+            to: 0x202,
+            kind: EdgeKind::Jump
+        }));
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = Analysis::new(&ROM).to_dot();
+
+        assert!(dot.starts_with("digraph rom {"));
+        assert!(dot.contains("\"0x200\" -> \"0x20a\" [label=\"call\", style=dashed];"));
+        assert!(dot.contains("\"0x206\" -> \"0x20e\" [label=\"V0 +\", style=dotted, color=red];"));
+    }
+
+    #[test]
+    fn test_pong() {
+        let analysis = Analysis::new(include_bytes!("../../../roms/PONG"));
+
+        assert!(analysis.invalid.is_empty());
+        assert!(!analysis.subroutines.is_empty());
+        assert!(analysis
+            .data
+            .values()
+            .any(|kind| matches!(kind, DataKind::Sprite(_))));
+    }
+}
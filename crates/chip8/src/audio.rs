@@ -0,0 +1,126 @@
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+/// Renders the CHIP-8 beep as a square wave whenever the sound timer is active.
+#[derive(Debug, Clone)]
+pub struct Beeper {
+    pub sample_rate: u32,
+    pub frequency: f32,
+    pub volume: f32,
+    phase: f32,
+}
+
+impl Beeper {
+    pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+    pub const DEFAULT_FREQUENCY: f32 = 440.0;
+    pub const DEFAULT_VOLUME: f32 = 0.25;
+
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            frequency: Self::DEFAULT_FREQUENCY,
+            volume: Self::DEFAULT_VOLUME,
+            phase: 0.0,
+        }
+    }
+
+    /// Number of samples covering one 60 Hz timer tick.
+    pub fn samples_per_frame(&self) -> usize {
+        (self.sample_rate / 60) as usize
+    }
+
+    /// Fills `out` with the square wave if `active`, or silence otherwise. The phase carries over
+    /// between calls so consecutive buffers join without clicks.
+    pub fn render(&mut self, active: bool, out: &mut [f32]) {
+        let step = self.frequency / self.sample_rate as f32;
+
+        for sample in out.iter_mut() {
+            *sample = if !active {
+                0.0
+            } else if self.phase < 0.5 {
+                self.volume
+            } else {
+                -self.volume
+            };
+
+            self.phase += step;
+            self.phase -= self.phase as u32 as f32;
+        }
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SAMPLE_RATE)
+    }
+}
+
+/// Writes mono samples in the range -1.0..=1.0 as a 16-bit PCM WAV file.
+#[cfg(feature = "std")]
+pub fn write_wav<W: Write>(mut writer: W, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let data_size = (samples.len() * 2) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?; // Chunk size
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // Mono
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * 2).to_le_bytes())?; // Byte rate
+    writer.write_all(&2u16.to_le_bytes())?; // Block align
+    writer.write_all(&16u16.to_le_bytes())?; // Bits per sample
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&pcm.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut beeper = Beeper::new(8);
+        beeper.frequency = 2.0;
+        beeper.volume = 0.5;
+        let mut out = [0.0; 8];
+
+        beeper.render(true, &mut out);
+
+        assert_eq!([0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5], out);
+    }
+
+    #[test]
+    fn test_render_silent() {
+        let mut beeper = Beeper::default();
+        let mut out = [1.0; 16];
+
+        beeper.render(false, &mut out);
+
+        assert!(out.iter().all(|&sample| sample == 0.0));
+        assert_eq!(735, beeper.samples_per_frame());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_write_wav() {
+        let mut wav = Vec::new();
+
+        write_wav(&mut wav, 8000, &[0.0, 1.0, -1.0]).unwrap();
+
+        assert_eq!(44 + 6, wav.len());
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(b"WAVE", &wav[8..12]);
+        assert_eq!(8000, u32::from_le_bytes(wav[24..28].try_into().unwrap()));
+        assert_eq!(&[0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80], &wav[44..]);
+    }
+}
@@ -1,18 +1,43 @@
+#[cfg(feature = "std")]
+use std::io::Read;
+#[cfg(feature = "std")]
 use std::path::Path;
 
+#[cfg(feature = "std")]
 use log::info;
 
-use crate::error::ExecutionError;
+use alloc::vec::Vec;
+
+use crate::coverage::{Coverage, CoverageReport};
+use crate::error::{ExecutionError, RomError};
+use crate::instruction::Instruction;
+use crate::sanitizer::{Finding, Sanitizer};
+use crate::snapshot::{self, SnapshotError};
+use crate::virtual_machine::FONTS_SIZE;
 use crate::VirtualMachine;
-use crate::PROGRAM_START_ADDRESS;
-use crate::{Platform, State};
+use crate::{Halt, Platform, State};
+use crate::{MAX_ROM_SIZE, PROGRAM_START_ADDRESS};
+
+/// A snapshot of the CPU registers and timers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub dt: u8,
+    pub st: u8,
+}
 
 #[derive(Debug)]
 pub struct Chip8 {
     pub state: State,
     pub platform: Platform,
     vm: VirtualMachine,
+    rom: Vec<u8>,
     program_size: u16,
+    halt: Option<Halt>,
+    idle_skip: bool,
 }
 
 impl Chip8 {
@@ -21,75 +46,300 @@ impl Chip8 {
             vm: VirtualMachine::new(),
             platform,
             state: State::Off,
+            rom: Vec::new(),
             program_size: 0,
+            halt: None,
+            idle_skip: true,
         }
     }
 
-    pub fn load_rom(&mut self, rom_path: &Path) -> Result<(), std::io::Error> {
-        let rom = std::fs::read(rom_path)?;
-        self.program_size = rom.len() as u16;
+    #[cfg(feature = "std")]
+    pub fn load_rom(&mut self, rom_path: &Path) -> Result<(), RomError> {
+        info!("Loading {}", rom_path.display());
+        self.load_rom_reader(std::fs::File::open(rom_path)?)
+    }
+
+    /// Reads a ROM from `reader`, stopping a byte past the largest ROM that fits in memory.
+    #[cfg(feature = "std")]
+    pub fn load_rom_reader(&mut self, reader: impl Read) -> Result<(), RomError> {
+        let mut rom = Vec::new();
+        reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut rom)?;
+        self.load_rom_bytes(&rom)
+    }
 
-        if rom.len() > 0x1000 - PROGRAM_START_ADDRESS as usize {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("ROM is too big to fit in memory: {} bytes", rom.len()),
-            ));
+    /// Starts `rom` on a fresh machine. The machine is left untouched if the ROM is rejected.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > MAX_ROM_SIZE {
+            return Err(RomError::TooLarge(rom.len()));
         }
 
-        info!("Loading {}", rom_path.display());
-        self.vm.ram
-            [PROGRAM_START_ADDRESS as usize..(PROGRAM_START_ADDRESS + rom.len() as u16) as usize]
-            .copy_from_slice(&rom);
-        self.state = State::Running;
+        self.rom = rom.to_vec();
+        self.restart();
 
         Ok(())
     }
 
+    /// Restarts the loaded ROM from a fresh machine.
     pub fn reset(&mut self) -> Result<(), ExecutionError> {
-        self.vm = VirtualMachine::new();
-        self.vm
-            .execute(0x00E0, &self.platform)?;
+        self.restart();
+        self.vm.execute(0x00E0, &self.platform)?;
         self.vm.pc = PROGRAM_START_ADDRESS;
 
         Ok(())
     }
 
+    /// Seeds the random number generator behind CXKK, making runs reproducible.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.vm.rng.seed(seed);
+    }
+
     pub fn reset_keys(&mut self) {
         self.vm.keypad = [false; 16];
     }
 
+    /// Executes one instruction, finishing the program when it exits with 00FD or jumps to
+    /// itself.
     pub fn step(&mut self) -> Result<(), ExecutionError> {
-        if self.vm.pc >= PROGRAM_START_ADDRESS + self.program_size {
-            self.state = State::Finished;
-            return Ok(());
-        }
-
         if self.state != State::Running {
             return Ok(());
         }
 
+        let pc = self.vm.pc;
         let opcode = self.opcode()?;
+        let instruction = Instruction::decode(opcode)?;
+        if let Some(coverage) = &mut self.vm.coverage {
+            coverage.record_execute(pc);
+        }
+        if let Some(sanitizer) = &mut self.vm.sanitizer {
+            sanitizer.fetch(pc);
+        }
+
+        match instruction {
+            Instruction::Exit => {
+                self.finish(Halt::Exit);
+                return Ok(());
+            }
+            Instruction::Jp(nnn) if nnn == pc => {
+                self.finish(Halt::SelfJump(pc));
+                return Ok(());
+            }
+            _ => {}
+        }
+
         self.vm.execute(opcode, &self.platform)?;
 
-        if self.vm.dt > 0 {
-            self.vm.dt -= 1;
+        let waiting = matches!(instruction, Instruction::LdVxK(_)) && self.vm.pc == pc;
+        self.halt =
+            (waiting && self.vm.dt == 0 && self.vm.st == 0).then_some(Halt::WaitingForKey(pc));
+
+        Ok(())
+    }
+
+    /// Why the program stopped: set once it has finished, or while it waits on a key with
+    /// nothing else left to happen.
+    pub fn halt(&self) -> Option<Halt> {
+        self.halt
+    }
+
+    /// Runs one 60 Hz frame: `tick_rate` instructions followed by a single timer tick.
+    /// Once the program has finished only the timers tick.
+    pub fn run_frame(&mut self) -> Result<(), ExecutionError> {
+        for _ in 0..self.platform.tick_rate {
+            if self.idle_skip && self.idle_loop() {
+                break;
+            }
+            self.step()?;
         }
 
-        if self.vm.st > 0 {
-            self.vm.st -= 1;
+        if self.timers_running() {
+            self.vm.tick_timers();
         }
 
         Ok(())
     }
 
+    /// Enables or disables skipping to the next timer tick when the program spins in an idle loop.
+    /// Enabled by default.
+    pub fn set_idle_skip(&mut self, enabled: bool) {
+        self.idle_skip = enabled;
+    }
+
+    /// Whether the program is about to spin in a `FX07 / 3X00 / 1NNN` loop back to the `FX07`
+    /// while the delay timer runs. The loop writes no memory and checks no keys, so nothing can
+    /// change before the next timer tick and the rest of the frame can be skipped.
+    fn idle_loop(&self) -> bool {
+        let pc = self.vm.pc;
+        if self.state != State::Running || self.vm.dt == 0 {
+            return false;
+        }
+
+        matches!(
+            (
+                self.instruction_at(pc),
+                self.instruction_at(pc.wrapping_add(2)),
+                self.instruction_at(pc.wrapping_add(4)),
+            ),
+            (
+                Some(Instruction::LdVxDt(x)),
+                Some(Instruction::SeByte(y, 0)),
+                Some(Instruction::Jp(nnn)),
+            ) if x == y && nnn == pc
+        )
+    }
+
+    /// Whether the sound timer is non-zero, meaning the buzzer should be sounding.
+    pub fn sound_active(&self) -> bool {
+        self.timers_running() && self.vm.st > 0
+    }
+
+    /// Timers keep counting down after the program halts, like the interrupt-driven timers of
+    /// the original machines, so a closing beep plays out.
+    fn timers_running(&self) -> bool {
+        matches!(self.state, State::Running | State::Finished)
+    }
+
     pub fn key_down(&mut self, i: usize) {
         self.vm.keypad[i] = true;
     }
 
+    pub fn key_up(&mut self, i: usize) {
+        self.vm.keypad[i] = false;
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            v: self.vm.registers,
+            i: self.vm.i,
+            pc: self.vm.pc,
+            sp: self.vm.sp,
+            dt: self.vm.dt,
+            st: self.vm.st,
+        }
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.vm.ram
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.vm.ram
+    }
+
+    /// Serializes the machine into a `SNAPSHOT_SIZE` byte snapshot. Coverage and sanitizer data
+    /// are not included.
+    pub fn save_state(&self) -> Vec<u8> {
+        snapshot::encode(&self.vm, self.state, self.program_size)
+    }
+
+    /// Restores a snapshot from `save_state`. The halt reason is not stored, but found again from
+    /// the instruction the restored program stopped on.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let (state, program_size) = snapshot::decode(bytes, &mut self.vm)?;
+        self.state = state;
+        self.program_size = program_size;
+        self.halt = self.restored_halt();
+
+        Ok(())
+    }
+
+    /// The halt `step` would have recorded for a machine stopped at its current PC.
+    fn restored_halt(&self) -> Option<Halt> {
+        let pc = self.vm.pc;
+        match (self.state, self.instruction_at(pc)?) {
+            (State::Finished, Instruction::Exit) => Some(Halt::Exit),
+            (State::Finished, Instruction::Jp(nnn)) if nnn == pc => Some(Halt::SelfJump(pc)),
+            (State::Running | State::Paused, Instruction::LdVxK(_))
+                if self.vm.dt == 0 && self.vm.st == 0 =>
+            {
+                Some(Halt::WaitingForKey(pc))
+            }
+            _ => None,
+        }
+    }
+
     pub fn has_color(&self, x: u16, y: u16) -> bool {
         self.vm.video[(y as usize * self.platform.video_width as usize) + x as usize] == 0x1
     }
 
+    /// The display row by row, one byte per pixel that is 1 where lit.
+    pub fn framebuffer(&self) -> &[u8] {
+        let size = self.platform.video_width as usize * self.platform.video_height as usize;
+        &self.vm.video[..size]
+    }
+
+    pub fn enable_coverage(&mut self) {
+        self.vm.coverage.get_or_insert_with(Coverage::new);
+    }
+
+    pub fn coverage(&self) -> Option<CoverageReport<'_>> {
+        self.vm.coverage.as_ref().map(|coverage| CoverageReport {
+            coverage,
+            ram: &self.vm.ram,
+            range: PROGRAM_START_ADDRESS..PROGRAM_START_ADDRESS + self.program_size,
+        })
+    }
+
+    /// Enables the sanitizer, poisoning every byte of RAM that was not loaded by the program.
+    pub fn enable_sanitizer(&mut self) {
+        if self.vm.sanitizer.is_none() {
+            self.vm.sanitizer = Some(Sanitizer::new());
+            self.poison();
+        }
+    }
+
+    pub fn sanitizer_findings(&self) -> &[Finding] {
+        self.vm
+            .sanitizer
+            .as_ref()
+            .map_or(&[], |sanitizer| sanitizer.findings())
+    }
+
+    fn finish(&mut self, halt: Halt) {
+        if let Some(sanitizer) = &mut self.vm.sanitizer {
+            sanitizer.exit(self.vm.sp);
+        }
+        self.halt = Some(halt);
+        self.state = State::Finished;
+    }
+
+    fn restart(&mut self) {
+        let coverage = self.vm.coverage.take();
+        let sanitizer = self.vm.sanitizer.take();
+        let rng = self.vm.rng.clone();
+        self.vm = VirtualMachine::new();
+        self.vm.coverage = coverage;
+        self.vm.sanitizer = sanitizer;
+        self.vm.rng = rng;
+
+        let start = PROGRAM_START_ADDRESS as usize;
+        self.vm.ram[start..start + self.rom.len()].copy_from_slice(&self.rom);
+        self.program_size = self.rom.len() as u16;
+        self.halt = None;
+        self.poison();
+        if !self.rom.is_empty() {
+            self.state = State::Running;
+        }
+    }
+
+    fn poison(&mut self) {
+        if let Some(sanitizer) = &mut self.vm.sanitizer {
+            let start = PROGRAM_START_ADDRESS as usize;
+            sanitizer.poison(
+                &mut self.vm.ram,
+                0..FONTS_SIZE,
+                start..start + self.program_size as usize,
+            );
+        }
+    }
+
+    fn instruction_at(&self, address: u16) -> Option<Instruction> {
+        let bytes = self.vm.ram.get(address as usize..address as usize + 2)?;
+        Instruction::decode(u16::from(bytes[0]) << 8 | u16::from(bytes[1])).ok()
+    }
+
     fn opcode(&self) -> Result<u16, ExecutionError> {
         if (self.vm.pc as usize + 1) >= self.vm.ram.len() {
             return Err(ExecutionError::InvalidOpcode(self.vm.pc));
@@ -105,3 +355,132 @@ impl Default for Chip8 {
         Self::new(Platform::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_rom_bytes() {
+        let mut chip8 = Chip8::default();
+        chip8.load_rom_bytes(&[0x60, 0x2A]).unwrap();
+
+        assert!(matches!(chip8.load_rom_bytes(&[]), Err(RomError::Empty)));
+        assert!(matches!(
+            chip8.load_rom_bytes(&[0; MAX_ROM_SIZE + 1]),
+            Err(RomError::TooLarge(3585))
+        ));
+        #[cfg(feature = "std")]
+        assert!(matches!(
+            chip8.load_rom_reader(std::io::repeat(0x12)),
+            Err(RomError::TooLarge(3585))
+        ));
+        assert_eq!(0x60, chip8.ram()[0x200]);
+
+        chip8.step().unwrap();
+        assert_eq!(0x2A, chip8.registers().v[0]);
+        assert_eq!(State::Running, chip8.state);
+
+        chip8.load_rom_bytes(&[0x61, 0x01]).unwrap();
+        assert_eq!(State::Running, chip8.state);
+        assert_eq!(0, chip8.registers().v[0]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_load_rom_reader() {
+        let mut chip8 = Chip8::default();
+        chip8
+            .load_rom_reader(std::io::Cursor::new([0x60, 0x2A]))
+            .unwrap();
+        assert_eq!([0x60, 0x2A], chip8.ram()[0x200..0x202]);
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut chip8 = Chip8::default();
+        chip8.load_rom_bytes(&[0x60, 0x01, 0x00, 0xFD]).unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(State::Finished, chip8.state);
+        assert_eq!(Some(Halt::Exit), chip8.halt());
+
+        // Running past the end of the ROM into code it copied there is not a halt.
+        chip8
+            .load_rom_bytes(&[0x60, 0x13, 0x61, 0x00, 0xA3, 0x00, 0xF1, 0x55, 0x13, 0x00])
+            .unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(State::Finished, chip8.state);
+        assert_eq!(Some(Halt::SelfJump(0x300)), chip8.halt());
+
+        let state = chip8.save_state();
+        let mut restored = Chip8::default();
+        restored.load_state(&state).unwrap();
+        assert_eq!(State::Finished, restored.state);
+        assert_eq!(Some(Halt::SelfJump(0x300)), restored.halt());
+
+        chip8.reset().unwrap();
+        assert_eq!(None, chip8.halt());
+        assert_eq!(State::Running, chip8.state);
+    }
+
+    #[test]
+    fn test_waiting_for_key() {
+        let mut chip8 = Chip8::default();
+        chip8
+            .load_rom_bytes(&[0x60, 0x02, 0xF0, 0x15, 0xF1, 0x0A, 0x12, 0x06])
+            .unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(None, chip8.halt());
+        chip8.run_frame().unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(Some(Halt::WaitingForKey(0x204)), chip8.halt());
+        assert_eq!(State::Running, chip8.state);
+        let mut restored = Chip8::default();
+        restored.load_state(&chip8.save_state()).unwrap();
+        assert_eq!(Some(Halt::WaitingForKey(0x204)), restored.halt());
+
+        chip8.key_down(5);
+        chip8.run_frame().unwrap();
+        assert_eq!(5, chip8.registers().v[1]);
+        assert_eq!(Some(Halt::SelfJump(0x206)), chip8.halt());
+    }
+
+    #[test]
+    fn test_idle_skip() {
+        // Waits for the delay timer in a FX07 / 3X00 / 1NNN loop at 0x204, then sets V1 and halts.
+        let rom = [
+            0x60, 0x03, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04, 0x61, 0x01, 0x12, 0x0C,
+        ];
+        let mut skipping = Chip8::default();
+        skipping.load_rom_bytes(&rom).unwrap();
+        let mut spinning = Chip8::default();
+        spinning.set_idle_skip(false);
+        spinning.load_rom_bytes(&rom).unwrap();
+
+        skipping.run_frame().unwrap();
+        assert_eq!(0x204, skipping.registers().pc);
+
+        for _ in 0..4 {
+            skipping.run_frame().unwrap();
+            spinning.run_frame().unwrap();
+        }
+        assert_eq!(1, skipping.registers().v[1]);
+        assert_eq!(spinning.registers().v[1], skipping.registers().v[1]);
+        assert_eq!(spinning.registers().dt, skipping.registers().dt);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut chip8 = Chip8::default();
+        chip8.load_rom_bytes(&[0x60, 0x2A, 0x12, 0x00]).unwrap();
+        chip8.ram_mut()[0x300] = 1;
+        chip8.step().unwrap();
+
+        chip8.reset().unwrap();
+        assert_eq!(0, chip8.ram()[0x300]);
+        assert_eq!(0, chip8.registers().v[0]);
+        assert_eq!(0x60, chip8.ram()[0x200]);
+        chip8.step().unwrap();
+        assert_eq!(0x2A, chip8.registers().v[0]);
+    }
+}
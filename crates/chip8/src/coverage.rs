@@ -0,0 +1,313 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::Range;
+
+use bitflags::bitflags;
+
+use crate::disassembler::disassemble;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub struct Access: u8 {
+        const EXECUTED = 0b0000_0001;  // Fetched as part of an opcode
+        const READ = 0b0000_0010;  // Read as data through I (DXYN, FX65)
+        const WRITTEN = 0b0000_0100;  // Written through I (FX33, FX55)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    access: [Access; 4096],
+    hits: [u32; 4096],
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            access: [Access::empty(); 4096],
+            hits: [0; 4096],
+        }
+    }
+
+    pub fn record_execute(&mut self, addr: u16) {
+        let addr = addr as usize % self.access.len();
+        self.hits[addr] = self.hits[addr].saturating_add(1);
+        self.access[addr] |= Access::EXECUTED;
+        self.access[(addr + 1) % self.access.len()] |= Access::EXECUTED;
+    }
+
+    pub fn record_read(&mut self, addr: u16) {
+        self.access[addr as usize % self.access.len()] |= Access::READ;
+    }
+
+    pub fn record_write(&mut self, addr: u16) {
+        self.access[addr as usize % self.access.len()] |= Access::WRITTEN;
+    }
+
+    pub fn access(&self, addr: u16) -> Access {
+        self.access[addr as usize % self.access.len()]
+    }
+
+    /// Number of times an instruction was fetched starting at `addr`.
+    pub fn hits(&self, addr: u16) -> u32 {
+        self.hits[addr as usize % self.hits.len()]
+    }
+
+    pub fn count(&self, range: Range<u16>, access: Access) -> usize {
+        range
+            .filter(|&addr| self.access(addr).contains(access))
+            .count()
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A view of the coverage of a loaded program, pairing the recorded accesses with the RAM they
+/// refer to so it can be exported.
+#[derive(Debug)]
+pub struct CoverageReport<'a> {
+    pub coverage: &'a Coverage,
+    pub ram: &'a [u8],
+    pub range: Range<u16>,
+}
+
+impl CoverageReport<'_> {
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let bytes = self.range.len();
+
+        writeln!(json, "{{").unwrap();
+        writeln!(
+            json,
+            "  \"range\": {{ \"start\": {}, \"end\": {} }},",
+            self.range.start, self.range.end
+        )
+        .unwrap();
+        writeln!(
+            json,
+            "  \"summary\": {{ \"bytes\": {}, \"executed\": {}, \"read\": {}, \"written\": {} }},",
+            bytes,
+            self.coverage.count(self.range.clone(), Access::EXECUTED),
+            self.coverage.count(self.range.clone(), Access::READ),
+            self.coverage.count(self.range.clone(), Access::WRITTEN),
+        )
+        .unwrap();
+        writeln!(json, "  \"bytes\": [").unwrap();
+
+        let touched: Vec<u16> = (0..self.ram.len() as u16)
+            .filter(|&addr| !self.coverage.access(addr).is_empty())
+            .collect();
+        for (n, &addr) in touched.iter().enumerate() {
+            let access = self.coverage.access(addr);
+            writeln!(
+                json,
+                "    {{ \"address\": {}, \"hits\": {}, \"executed\": {}, \"read\": {}, \"written\": {} }}{}",
+                addr,
+                self.coverage.hits(addr),
+                access.contains(Access::EXECUTED),
+                access.contains(Access::READ),
+                access.contains(Access::WRITTEN),
+                if n + 1 < touched.len() { "," } else { "" }
+            )
+            .unwrap();
+        }
+
+        writeln!(json, "  ]").unwrap();
+        writeln!(json, "}}").unwrap();
+
+        json
+    }
+
+    /// Exports instruction hits in the LCOV tracefile format, using addresses as line numbers.
+    pub fn to_lcov(&self, source_name: &str) -> String {
+        let mut lcov = String::new();
+        let mut found = 0;
+        let mut hit = 0;
+
+        writeln!(lcov, "TN:").unwrap();
+        writeln!(lcov, "SF:{source_name}").unwrap();
+        for addr in self.instructions() {
+            let hits = self.coverage.hits(addr);
+            writeln!(lcov, "DA:{addr},{hits}").unwrap();
+            found += 1;
+            if hits > 0 {
+                hit += 1;
+            }
+        }
+        writeln!(lcov, "LH:{hit}").unwrap();
+        writeln!(lcov, "LF:{found}").unwrap();
+        writeln!(lcov, "end_of_record").unwrap();
+
+        lcov
+    }
+
+    /// Disassembles the program, prefixing instructions that were never executed with `!`.
+    pub fn disassembly(&self) -> String {
+        let mut listing = String::new();
+
+        for addr in self.instructions() {
+            let hits = self.coverage.hits(addr);
+            let opcode = self.opcode(addr);
+            let text = disassemble(opcode).unwrap_or_else(|| format!("DW {opcode:#06x}"));
+
+            let mut notes = Vec::new();
+            for offset in 0..2 {
+                let access = self.coverage.access(addr + offset);
+                if access.contains(Access::READ) && !notes.contains(&"read") {
+                    notes.push("read");
+                }
+                if access.contains(Access::WRITTEN) && !notes.contains(&"written") {
+                    notes.push("written");
+                }
+            }
+
+            write!(
+                listing,
+                "{} {:#06x}  {:04X}  {:>6}  {:<18}",
+                if hits == 0 { '!' } else { ' ' },
+                addr,
+                opcode,
+                hits,
+                text
+            )
+            .unwrap();
+            if !notes.is_empty() {
+                write!(listing, "; {}", notes.join(", ")).unwrap();
+            }
+            listing.truncate(listing.trim_end().len());
+            listing.push('\n');
+        }
+
+        listing
+    }
+
+    /// Walks the range two bytes at a time, realigning on instructions that were executed from an
+    /// odd offset, both those starting a byte later and those whose second byte comes first.
+    fn instructions(&self) -> Vec<u16> {
+        let mut addrs = Vec::new();
+        let mut addr = self.range.start;
+
+        while addr < self.range.end {
+            let next_executed = addr + 1 < self.range.end && self.coverage.hits(addr + 1) > 0;
+            if self.coverage.hits(addr) == 0
+                && (next_executed || self.coverage.access(addr).contains(Access::EXECUTED))
+            {
+                addr += 1;
+                continue;
+            }
+
+            addrs.push(addr);
+            addr += 2;
+        }
+
+        addrs
+    }
+
+    fn opcode(&self, addr: u16) -> u16 {
+        let hi = self.ram.get(addr as usize).copied().unwrap_or(0);
+        let lo = self.ram.get(addr as usize + 1).copied().unwrap_or(0);
+        u16::from(hi) << 8 | u16::from(lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report<'a>(coverage: &'a Coverage, ram: &'a [u8]) -> CoverageReport<'a> {
+        CoverageReport {
+            coverage,
+            ram,
+            range: 0x200..0x206,
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut coverage = Coverage::new();
+
+        coverage.record_execute(0x200);
+        coverage.record_execute(0x200);
+        coverage.record_read(0x300);
+        coverage.record_write(0x301);
+
+        assert_eq!(2, coverage.hits(0x200));
+        assert_eq!(0, coverage.hits(0x201));
+        assert_eq!(Access::EXECUTED, coverage.access(0x201));
+        assert_eq!(Access::READ, coverage.access(0x300));
+        assert_eq!(Access::WRITTEN, coverage.access(0x301));
+    }
+
+    #[test]
+    fn test_lcov() {
+        let mut coverage = Coverage::new();
+        let ram = [0; 4096];
+        coverage.record_execute(0x200);
+
+        let lcov = report(&coverage, &ram).to_lcov("TEST");
+
+        assert!(lcov.contains("SF:TEST\n"));
+        assert!(lcov.contains("DA:512,1\n"));
+        assert!(lcov.contains("DA:514,0\n"));
+        assert!(lcov.contains("LH:1\nLF:3\n"));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut coverage = Coverage::new();
+        let ram = [0; 4096];
+        coverage.record_execute(0x200);
+        coverage.record_write(0x204);
+
+        let json = report(&coverage, &ram).to_json();
+
+        assert!(json.contains("\"executed\": 2, \"read\": 0, \"written\": 1"));
+        assert!(json.contains(
+            "{ \"address\": 516, \"hits\": 0, \"executed\": false, \"read\": false, \"written\": true }\n"
+        ));
+    }
+
+    #[test]
+    fn test_disassembly() {
+        let mut coverage = Coverage::new();
+        let mut ram = [0; 4096];
+        ram[0x200..0x206].copy_from_slice(&[0x6A, 0x02, 0x12, 0x00, 0xF0, 0x90]);
+        coverage.record_execute(0x200);
+        coverage.record_execute(0x202);
+        coverage.record_read(0x204);
+
+        let listing = report(&coverage, &ram).disassembly();
+        let lines: Vec<&str> = listing.lines().collect();
+
+        assert_eq!("  0x0200  6A02       1  LD VA, 0x02", lines[0]);
+        assert_eq!("  0x0202  1200       1  JP 0x200", lines[1]);
+        assert_eq!("! 0x0204  F090       0  DW 0xf090         ; read", lines[2]);
+    }
+
+    #[test]
+    fn test_odd_address() {
+        let mut coverage = Coverage::new();
+        let mut ram = [0; 4096];
+        // 0x200: JP 0x203, a padding byte, then LD VA, 0x02 and JP 0x205 from 0x203.
+        ram[0x200..0x207].copy_from_slice(&[0x12, 0x03, 0x00, 0x6A, 0x02, 0x12, 0x05]);
+        for addr in [0x200, 0x203, 0x205] {
+            coverage.record_execute(addr);
+        }
+        let report = CoverageReport {
+            coverage: &coverage,
+            ram: &ram,
+            range: 0x200..0x207,
+        };
+
+        assert_eq!(vec![0x200, 0x203, 0x205], report.instructions());
+        let listing = report.disassembly();
+        assert!(listing.contains("  0x0203  6A02       1  LD VA, 0x02\n"));
+        assert!(!listing.contains("0x0202"));
+    }
+}
@@ -0,0 +1,672 @@
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+use crate::analysis::{Analysis, DataKind};
+use crate::instruction::Instruction;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Construct {
+    /// `loop` at `start`, `again` in place of the backward jump at `again`.
+    Loop { start: u16, again: u16 },
+    /// `if ... begin` in place of the skip and jump at `start`, optionally `else` in place of the
+    /// jump at `otherwise`, and `end` before `end`.
+    If {
+        start: u16,
+        otherwise: Option<u16>,
+        end: u16,
+    },
+}
+
+impl Construct {
+    fn range(&self) -> (u16, u16) {
+        match *self {
+            Self::Loop { start, again } => (start, again + 2),
+            Self::If { start, end, .. } => (start, end),
+        }
+    }
+
+    /// The ranges other constructs can sit inside without crossing one of this construct's
+    /// keywords.
+    fn bodies(&self) -> Vec<(u16, u16)> {
+        match *self {
+            Self::Loop { start, again } => vec![(start, again)],
+            Self::If {
+                start,
+                otherwise: None,
+                end,
+            } => vec![(start + 4, end)],
+            Self::If {
+                start,
+                otherwise: Some(otherwise),
+                end,
+            } => vec![(start + 4, otherwise), (otherwise + 2, end)],
+        }
+    }
+
+    fn nests_with(&self, other: &Self) -> bool {
+        let inside = |(start, end): (u16, u16), outer: &Self| {
+            outer
+                .bodies()
+                .iter()
+                .any(|&(body_start, body_end)| body_start <= start && end <= body_end)
+        };
+        let (a_start, a_end) = self.range();
+        let (b_start, b_end) = other.range();
+
+        a_end <= b_start
+            || b_end <= a_start
+            || inside(self.range(), other)
+            || inside(other.range(), self)
+    }
+}
+
+/// Turns a ROM into structured Octo source. Every statement assembles back to the bytes it was
+/// decompiled from, so anything that cannot be structured falls back to labels and jumps, and
+/// anything that cannot be expressed at all falls back to raw bytes.
+#[derive(Debug)]
+pub struct Decompiler {
+    analysis: Analysis,
+    starts: BTreeSet<u16>,
+    constructs: Vec<Construct>,
+    whiles: BTreeSet<u16>,
+    referenced: BTreeSet<u16>,
+}
+
+pub fn decompile(rom: &[u8]) -> String {
+    Decompiler::new(Analysis::new(rom)).source()
+}
+
+impl Decompiler {
+    pub fn new(analysis: Analysis) -> Self {
+        let mut decompiler = Self {
+            analysis,
+            starts: BTreeSet::new(),
+            constructs: Vec::new(),
+            whiles: BTreeSet::new(),
+            referenced: BTreeSet::new(),
+        };
+
+        decompiler.layout();
+        decompiler.find_loops();
+        decompiler.find_ifs();
+        decompiler.find_whiles();
+        decompiler.find_references();
+
+        decompiler
+    }
+
+    pub fn source(&self) -> String {
+        let mut source = String::new();
+        let mut depth = 0;
+        let mut consumed = BTreeSet::new();
+        let mut data_line = Vec::new();
+        let mut addr = self.analysis.entry;
+
+        writeln!(
+            source,
+            "# Decompiled from a {} byte ROM",
+            self.analysis.end - self.analysis.entry
+        )
+        .unwrap();
+
+        while addr < self.analysis.end {
+            let instruction = self.analysis.instructions.get(&addr).copied();
+            let ends = self
+                .constructs
+                .iter()
+                .filter(|c| matches!(c, Construct::If { end, .. } if *end == addr))
+                .count();
+            let label = self.reference_label(addr);
+
+            if !data_line.is_empty()
+                && (instruction.is_some() || label.is_some() || data_line.len() == 8)
+            {
+                self.flush_data(&mut source, depth, &mut data_line);
+            }
+
+            for _ in 0..ends {
+                depth -= 1;
+                writeln!(source, "{}end", indent(depth)).unwrap();
+            }
+
+            if let Some(label) = label {
+                match self.analysis.data.get(&addr) {
+                    Some(DataKind::Sprite(rows)) if instruction.is_none() => {
+                        writeln!(source, ": {label}  # sprite, {rows} rows").unwrap()
+                    }
+                    _ => writeln!(source, ": {label}").unwrap(),
+                }
+            }
+
+            for construct in &self.constructs {
+                if let Construct::Loop { start, .. } = construct {
+                    if *start == addr {
+                        writeln!(source, "{}loop", indent(depth)).unwrap();
+                        depth += 1;
+                    }
+                }
+            }
+
+            let Some(instruction) = instruction else {
+                if let Some(DataKind::Sprite(rows)) = self.analysis.data.get(&addr) {
+                    for row in 0..*rows {
+                        let row_addr = addr + row;
+                        if row_addr >= self.analysis.end
+                            || self.analysis.is_code(row_addr)
+                            || row > 0 && self.reference_label(row_addr).is_some()
+                        {
+                            break;
+                        }
+                        writeln!(
+                            source,
+                            "{}{:#010b}",
+                            indent(depth),
+                            self.analysis.byte(row_addr)
+                        )
+                        .unwrap();
+                        consumed.insert(row_addr);
+                    }
+                }
+
+                if !consumed.contains(&addr) {
+                    data_line.push(self.analysis.byte(addr));
+                }
+                addr += 1;
+                while consumed.contains(&addr) {
+                    addr += 1;
+                }
+                continue;
+            };
+
+            if consumed.contains(&addr) {
+                addr += 2;
+                continue;
+            }
+
+            if let Some(construct) = self.construct_at(addr) {
+                match construct {
+                    Construct::Loop { .. } => {
+                        depth -= 1;
+                        writeln!(source, "{}again", indent(depth)).unwrap();
+                    }
+                    Construct::If { start, .. } if start == addr => {
+                        writeln!(
+                            source,
+                            "{}if {} begin",
+                            indent(depth),
+                            condition(&instruction, false)
+                        )
+                        .unwrap();
+                        depth += 1;
+                        consumed.insert(addr + 2);
+                    }
+                    Construct::If { .. } => {
+                        writeln!(source, "{}else", indent(depth - 1)).unwrap();
+                    }
+                }
+            } else if self.whiles.contains(&addr) {
+                writeln!(
+                    source,
+                    "{}while {}",
+                    indent(depth),
+                    condition(&instruction, false)
+                )
+                .unwrap();
+                consumed.insert(addr + 2);
+            } else if instruction.is_skip() && self.closes_loop(addr + 2) {
+                writeln!(
+                    source,
+                    "{}if {} then again",
+                    indent(depth),
+                    condition(&instruction, true)
+                )
+                .unwrap();
+                depth -= 1;
+                consumed.insert(addr + 2);
+            } else if instruction.is_skip() {
+                match self.then_statement(addr) {
+                    Some(statement) => {
+                        writeln!(
+                            source,
+                            "{}if {} then {}",
+                            indent(depth),
+                            condition(&instruction, true),
+                            statement
+                        )
+                        .unwrap();
+                        consumed.insert(addr + 2);
+                    }
+                    None => self.raw(&mut source, depth, addr, &instruction),
+                }
+            } else {
+                writeln!(source, "{}{}", indent(depth), self.statement(&instruction)).unwrap();
+            }
+
+            addr += 2;
+        }
+
+        if !data_line.is_empty() {
+            self.flush_data(&mut source, depth, &mut data_line);
+        }
+        let ends = self
+            .constructs
+            .iter()
+            .filter(|c| matches!(c, Construct::If { end, .. } if *end >= self.analysis.end))
+            .count();
+        for _ in 0..ends {
+            depth -= 1;
+            writeln!(source, "{}end", indent(depth)).unwrap();
+        }
+
+        source
+    }
+
+    /// Records the address of every item the output walks through, so constructs and labels are
+    /// only placed where the walk will actually stop.
+    fn layout(&mut self) {
+        let mut addr = self.analysis.entry;
+
+        while addr < self.analysis.end {
+            self.starts.insert(addr);
+            addr += if self.analysis.instructions.contains_key(&addr) {
+                2
+            } else {
+                1
+            };
+        }
+    }
+
+    fn find_loops(&mut self) {
+        let mut candidates: Vec<Construct> = self
+            .analysis
+            .instructions
+            .iter()
+            .filter_map(|(&addr, instruction)| match *instruction {
+                Instruction::Jp(target) if target <= addr && self.is_statement(target) => {
+                    Some(Construct::Loop {
+                        start: target,
+                        again: addr,
+                    })
+                }
+                _ => None,
+            })
+            .filter(|c| self.is_statement(c.range().0) && self.is_code_range(c.range()))
+            .collect();
+
+        // Prefer the widest loops so inner backward jumps nest inside them.
+        candidates.sort_by_key(|c| (c.range().0, u16::MAX - c.range().1));
+        for candidate in candidates {
+            self.accept(candidate);
+        }
+    }
+
+    fn find_ifs(&mut self) {
+        let skips: Vec<u16> = self
+            .analysis
+            .instructions
+            .iter()
+            .filter(|(_, instruction)| instruction.is_skip())
+            .map(|(&addr, _)| addr)
+            .collect();
+
+        for addr in skips {
+            let Some(&Instruction::Jp(end)) = self.analysis.instructions.get(&(addr + 2)) else {
+                continue;
+            };
+            if end <= addr + 4
+                || !self.is_statement(addr + 2)
+                || !self.is_statement(end)
+                || self.label_at(addr + 2).is_some()
+                || self.construct_at(addr + 2).is_some()
+                || !self.is_code_range((addr, end))
+            {
+                continue;
+            }
+
+            let otherwise = match self.analysis.instructions.get(&(end - 2)) {
+                Some(&Instruction::Jp(after))
+                    if after > end
+                        && self.is_statement(end - 2)
+                        && self.is_statement(after)
+                        && self.construct_at(end - 2).is_none()
+                        && self.is_code_range((end, after)) =>
+                {
+                    Some((end - 2, after))
+                }
+                _ => None,
+            };
+
+            let accepted = otherwise.is_some_and(|(otherwise, after)| {
+                self.accept(Construct::If {
+                    start: addr,
+                    otherwise: Some(otherwise),
+                    end: after,
+                })
+            });
+            if !accepted {
+                self.accept(Construct::If {
+                    start: addr,
+                    otherwise: None,
+                    end,
+                });
+            }
+        }
+    }
+
+    fn find_whiles(&mut self) {
+        for (&addr, instruction) in &self.analysis.instructions {
+            let Some(&Instruction::Jp(exit)) = self.analysis.instructions.get(&(addr + 2)) else {
+                continue;
+            };
+            if !instruction.is_skip()
+                || self.construct_at(addr).is_some()
+                || self.construct_at(addr + 2).is_some()
+                || self.label_at(addr + 2).is_some()
+            {
+                continue;
+            }
+
+            let innermost = self
+                .constructs
+                .iter()
+                .filter(|c| {
+                    let (start, end) = c.range();
+                    start <= addr && addr + 4 <= end
+                })
+                .min_by_key(|c| c.range().1 - c.range().0);
+            if let Some(Construct::Loop { again, .. }) = innermost {
+                if *again + 2 == exit {
+                    self.whiles.insert(addr);
+                }
+            }
+        }
+    }
+
+    fn find_references(&mut self) {
+        for (&addr, instruction) in &self.analysis.instructions {
+            let structured = self.construct_at(addr).is_some()
+                || addr >= 2 && self.whiles.contains(&(addr - 2))
+                || self
+                    .constructs
+                    .iter()
+                    .any(|c| matches!(c, Construct::If { start, .. } if *start + 2 == addr));
+
+            match *instruction {
+                Instruction::Jp(nnn) if !structured => {
+                    self.referenced.insert(nnn);
+                }
+                Instruction::Call(nnn) | Instruction::LdI(nnn) | Instruction::JpV0(nnn) => {
+                    self.referenced.insert(nnn);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn accept(&mut self, candidate: Construct) -> bool {
+        if self.constructs.iter().all(|c| c.nests_with(&candidate)) {
+            self.constructs.push(candidate);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The construct whose keyword replaces the instruction at `addr`, if any.
+    fn construct_at(&self, addr: u16) -> Option<Construct> {
+        self.constructs.iter().copied().find(|c| match *c {
+            Construct::Loop { again, .. } => again == addr,
+            Construct::If {
+                start, otherwise, ..
+            } => start == addr || otherwise == Some(addr),
+        })
+    }
+
+    fn is_statement(&self, addr: u16) -> bool {
+        self.starts.contains(&addr) && self.analysis.instructions.contains_key(&addr)
+    }
+
+    fn is_code_range(&self, (start, end): (u16, u16)) -> bool {
+        (start..end)
+            .filter(|addr| self.starts.contains(addr))
+            .all(|addr| self.analysis.instructions.contains_key(&addr))
+    }
+
+    /// Labels are only used where they will be defined in the output.
+    fn label_at(&self, addr: u16) -> Option<String> {
+        if !self.starts.contains(&addr) {
+            return None;
+        }
+
+        let label = self.analysis.label(addr)?;
+        let is_instruction = self.analysis.instructions.contains_key(&addr);
+        let is_data_label = label.starts_with("sprite_") || label.starts_with("data_");
+        (is_instruction != is_data_label).then_some(label)
+    }
+
+    /// Like `label_at`, but leaves out labels only targeted by jumps that became constructs.
+    fn reference_label(&self, addr: u16) -> Option<String> {
+        if addr != self.analysis.entry && !self.referenced.contains(&addr) {
+            return None;
+        }
+
+        self.label_at(addr)
+    }
+
+    fn reference(&self, addr: u16) -> String {
+        self.reference_label(addr)
+            .unwrap_or_else(|| format!("{addr:#05x}"))
+    }
+
+    fn closes_loop(&self, addr: u16) -> bool {
+        matches!(self.construct_at(addr), Some(Construct::Loop { .. }))
+            && self.reference_label(addr).is_none()
+            && !self.constructs.iter().any(|c| {
+                c.range().0 == addr || matches!(c, Construct::If { end, .. } if *end == addr)
+            })
+    }
+
+    fn then_statement(&self, addr: u16) -> Option<String> {
+        let next = self.analysis.instructions.get(&(addr + 2))?;
+
+        if !self.is_statement(addr + 2)
+            || next.is_skip()
+            || self.reference_label(addr + 2).is_some()
+            || self.construct_at(addr + 2).is_some()
+            || self.constructs.iter().any(|c| {
+                let (start, end) = c.range();
+                start == addr + 2 || end == addr + 2
+            })
+        {
+            return None;
+        }
+
+        Some(self.statement(next))
+    }
+
+    fn statement(&self, instruction: &Instruction) -> String {
+        match *instruction {
+            Instruction::Cls => "clear".to_string(),
+            Instruction::Ret => "return".to_string(),
+            Instruction::Exit => "exit".to_string(),
+            Instruction::Jp(nnn) => format!("jump {}", self.reference(nnn)),
+            Instruction::Call(nnn) => match self.reference_label(nnn) {
+                Some(label) => label,
+                None => format!(":call {nnn:#05x}"),
+            },
+            Instruction::LdByte(x, nn) => format!("v{x:x} := {nn}"),
+            Instruction::AddByte(x, nn) => format!("v{x:x} += {nn}"),
+            Instruction::LdReg(x, y) => format!("v{x:x} := v{y:x}"),
+            Instruction::Or(x, y) => format!("v{x:x} |= v{y:x}"),
+            Instruction::And(x, y) => format!("v{x:x} &= v{y:x}"),
+            Instruction::Xor(x, y) => format!("v{x:x} ^= v{y:x}"),
+            Instruction::AddReg(x, y) => format!("v{x:x} += v{y:x}"),
+            Instruction::Sub(x, y) => format!("v{x:x} -= v{y:x}"),
+            Instruction::Shr(x, y) => format!("v{x:x} >>= v{y:x}"),
+            Instruction::Subn(x, y) => format!("v{x:x} =- v{y:x}"),
+            Instruction::Shl(x, y) => format!("v{x:x} <<= v{y:x}"),
+            Instruction::LdI(nnn) => format!("i := {}", self.reference(nnn)),
+            Instruction::JpV0(nnn) => format!("jump0 {}", self.reference(nnn)),
+            Instruction::Rnd(x, nn) => format!("v{x:x} := random {nn:#04x}"),
+            Instruction::Drw(x, y, n) => format!("sprite v{x:x} v{y:x} {n}"),
+            Instruction::LdVxDt(x) => format!("v{x:x} := delay"),
+            Instruction::LdVxK(x) => format!("v{x:x} := key"),
+            Instruction::LdDtVx(x) => format!("delay := v{x:x}"),
+            Instruction::LdStVx(x) => format!("buzzer := v{x:x}"),
+            Instruction::AddI(x) => format!("i += v{x:x}"),
+            Instruction::LdF(x) => format!("i := hex v{x:x}"),
+            Instruction::LdB(x) => format!("bcd v{x:x}"),
+            Instruction::LdIVx(x) => format!("save v{x:x}"),
+            Instruction::LdVxI(x) => format!("load v{x:x}"),
+            Instruction::SeByte(..)
+            | Instruction::SneByte(..)
+            | Instruction::SeReg(..)
+            | Instruction::SneReg(..)
+            | Instruction::Skp(_)
+            | Instruction::Sknp(_) => format!("if {} then", condition(instruction, true)),
+        }
+    }
+
+    fn raw(&self, source: &mut String, depth: usize, addr: u16, instruction: &Instruction) {
+        let opcode = self.analysis.opcode(addr);
+        writeln!(
+            source,
+            "{}{:#04x} {:#04x}  # {}",
+            indent(depth),
+            opcode >> 8,
+            opcode & 0xFF,
+            instruction
+        )
+        .unwrap();
+    }
+
+    fn flush_data(&self, source: &mut String, depth: usize, data_line: &mut Vec<u8>) {
+        let bytes: Vec<String> = data_line.iter().map(|b| format!("{b:#04x}")).collect();
+        writeln!(source, "{}{}", indent(depth), bytes.join(" ")).unwrap();
+        data_line.clear();
+    }
+}
+
+/// The condition under which a skip instruction skips, or when `negate` is set, the condition
+/// under which the following instruction runs.
+fn condition(instruction: &Instruction, negate: bool) -> String {
+    let (x, op, rhs) = match *instruction {
+        Instruction::SeByte(x, nn) => (x, ["==", "!="], nn.to_string()),
+        Instruction::SneByte(x, nn) => (x, ["!=", "=="], nn.to_string()),
+        Instruction::SeReg(x, y) => (x, ["==", "!="], format!("v{y:x}")),
+        Instruction::SneReg(x, y) => (x, ["!=", "=="], format!("v{y:x}")),
+        Instruction::Skp(x) => return format!("v{x:x} {}", ["key", "-key"][negate as usize]),
+        Instruction::Sknp(x) => return format!("v{x:x} {}", ["-key", "key"][negate as usize]),
+        _ => unreachable!("{instruction} is not a skip"),
+    };
+
+    format!("v{x:x} {} {rhs}", op[negate as usize])
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loop_and_then() {
+        // 0x200: LD V0, 0
+        // 0x202: ADD V0, 1
+        // 0x204: SE V0, 10
+        // 0x206: CLS
+        // 0x208: JP 0x202
+        let rom = [0x60, 0x00, 0x70, 0x01, 0x30, 0x0A, 0x00, 0xE0, 0x12, 0x02];
+
+        assert_eq!(
+            "# Decompiled from a 10 byte ROM\n\
+             : main\n\
+             \x20 v0 := 0\n\
+             \x20 loop\n\
+             \x20   v0 += 1\n\
+             \x20   if v0 != 10 then clear\n\
+             \x20 again\n",
+            decompile(&rom)
+        );
+    }
+
+    #[test]
+    fn test_if_else() {
+        // 0x200: SE V1, V2
+        // 0x202: JP 0x208
+        // 0x204: LD V3, 1
+        // 0x206: JP 0x20A
+        // 0x208: LD V3, 2
+        // 0x20A: RET
+        let rom = [
+            0x51, 0x20, 0x12, 0x08, 0x63, 0x01, 0x12, 0x0A, 0x63, 0x02, 0x00, 0xEE,
+        ];
+
+        let source = decompile(&rom);
+
+        assert!(source
+            .contains("  if v1 == v2 begin\n    v3 := 1\n  else\n    v3 := 2\n  end\n  return\n"));
+    }
+
+    #[test]
+    fn test_while() {
+        // 0x200: SKP V4
+        // 0x202: JP 0x206
+        // 0x204: JP 0x200
+        // 0x206: RET
+        let rom = [0xE4, 0x9E, 0x12, 0x06, 0x12, 0x00, 0x00, 0xEE];
+
+        assert!(decompile(&rom).contains("  loop\n    while v4 key\n  again\n"));
+    }
+
+    #[test]
+    fn test_conditional_again() {
+        // 0x200: LD V0, DT
+        // 0x202: SE V0, 0
+        // 0x204: JP 0x200
+        // 0x206: RET
+        let rom = [0xF0, 0x07, 0x30, 0x00, 0x12, 0x00, 0x00, 0xEE];
+
+        assert!(decompile(&rom)
+            .contains("  loop\n    v0 := delay\n    if v0 != 0 then again\n  return\n"));
+    }
+
+    #[test]
+    fn test_subroutines_and_sprites() {
+        // 0x200: CALL 0x204
+        // 0x202: JP 0x202
+        // 0x204: LD I, 0x20A
+        // 0x206: DRW V0, V1, 2
+        // 0x208: RET
+        // 0x20A: sprite
+        let rom = [
+            0x22, 0x04, 0x12, 0x02, 0xA2, 0x0A, 0xD0, 0x12, 0x00, 0xEE, 0xF0, 0x90, 0x01,
+        ];
+
+        let source = decompile(&rom);
+
+        assert!(source.contains("  sub_204\n  loop\n  again\n"));
+        assert!(source.contains(": sub_204\n  i := sprite_20a\n  sprite v0 v1 2\n  return\n"));
+        assert!(source
+            .ends_with(": sprite_20a  # sprite, 2 rows\n  0b11110000\n  0b10010000\n  0x01\n"));
+    }
+
+    #[test]
+    fn test_roms() {
+        for rom in [
+            include_bytes!("../../../roms/PONG").as_slice(),
+            include_bytes!("../../../roms/BRIX").as_slice(),
+            include_bytes!("../../../roms/INVADERS").as_slice(),
+        ] {
+            let source = decompile(rom);
+
+            assert!(source.contains(": main\n"));
+            assert!(!source.contains("\n: main\n  0x"));
+        }
+    }
+}
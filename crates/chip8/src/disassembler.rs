@@ -0,0 +1,29 @@
+use alloc::string::{String, ToString};
+
+use crate::instruction::Instruction;
+
+pub fn disassemble(opcode: u16) -> Option<String> {
+    Instruction::decode(opcode)
+        .ok()
+        .map(|instruction| instruction.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(Some("CLS".to_string()), disassemble(0x00E0));
+        assert_eq!(Some("JP 0x228".to_string()), disassemble(0x1228));
+        assert_eq!(Some("LD VA, 0x02".to_string()), disassemble(0x6A02));
+        assert_eq!(Some("DRW V0, V1, 5".to_string()), disassemble(0xD015));
+        assert_eq!(Some("LD V3, [I]".to_string()), disassemble(0xF365));
+    }
+
+    #[test]
+    fn test_disassemble_invalid() {
+        assert_eq!(None, disassemble(0x5121));
+        assert_eq!(None, disassemble(0xFFFF));
+    }
+}
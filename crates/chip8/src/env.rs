@@ -0,0 +1,436 @@
+//! A Gym-style environment for training agents on CHIP-8 games.
+//!
+//! Rewards and episode ends are defined per ROM as rules over RAM, so a game only needs to keep
+//! its score somewhere in memory, as most do for FX33 before drawing it.
+
+use std::thread;
+
+use crate::{Chip8, Platform, RomError, State};
+
+/// A number read from RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamValue {
+    /// A single byte.
+    Byte(u16),
+    /// Big-endian binary coded decimal digits, one per byte, as stored by FX33.
+    Bcd { address: u16, digits: u8 },
+}
+
+impl RamValue {
+    pub fn read(self, ram: &[u8]) -> u32 {
+        let byte = |address: u16| u32::from(ram[usize::from(address) % ram.len()]);
+        match self {
+            RamValue::Byte(address) => byte(address),
+            RamValue::Bcd { address, digits } => (0..u16::from(digits)).fold(0, |value, digit| {
+                value * 10 + byte(address.wrapping_add(digit))
+            }),
+        }
+    }
+}
+
+/// Rewards `weight` for every unit a value increases by, and penalises decreases likewise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reward {
+    pub value: RamValue,
+    pub weight: f32,
+}
+
+/// Ends the episode once a value compares with a threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Equals(RamValue, u32),
+    AtLeast(RamValue, u32),
+    AtMost(RamValue, u32),
+}
+
+impl Termination {
+    fn reached(self, ram: &[u8]) -> bool {
+        match self {
+            Termination::Equals(value, threshold) => value.read(ram) == threshold,
+            Termination::AtLeast(value, threshold) => value.read(ram) >= threshold,
+            Termination::AtMost(value, threshold) => value.read(ram) <= threshold,
+        }
+    }
+}
+
+/// The actions an agent picks from, each a set of keys held for the whole step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActionSet(Vec<u16>);
+
+impl ActionSet {
+    /// Actions from key masks, where bit `n` holds key `n`.
+    pub fn from_masks(masks: Vec<u16>) -> Self {
+        Self(masks)
+    }
+
+    /// Doing nothing, followed by pressing each of `keys` on its own.
+    pub fn from_keys(keys: &[u8]) -> Self {
+        let masks = std::iter::once(0)
+            .chain(keys.iter().map(|&key| 1 << (key & 0xF)))
+            .collect();
+        Self(masks)
+    }
+
+    /// Doing nothing or pressing any single key.
+    pub fn full() -> Self {
+        Self::from_keys(&(0..16).collect::<Vec<_>>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn keys(&self, action: usize) -> u16 {
+        self.0[action]
+    }
+}
+
+/// How a ROM is played: its actions, rewards and when an episode ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub actions: ActionSet,
+    pub rewards: Vec<Reward>,
+    pub terminations: Vec<Termination>,
+    /// Frames run per step with the action held, summing the rewards.
+    pub frame_skip: u32,
+    /// Ends the episode after this many frames.
+    pub max_frames: Option<u32>,
+}
+
+impl EnvConfig {
+    pub fn new(actions: ActionSet) -> Self {
+        Self {
+            actions,
+            rewards: Vec::new(),
+            terminations: Vec::new(),
+            frame_skip: 4,
+            max_frames: None,
+        }
+    }
+
+    /// PONG by Paul Vervalin, playing the left paddle. The score is drawn from BCD at 0x2F2, with
+    /// the left player in the tens and the right player in the units.
+    pub fn pong() -> Self {
+        Self {
+            rewards: vec![
+                Reward {
+                    value: RamValue::Byte(0x2F3),
+                    weight: 1.0,
+                },
+                Reward {
+                    value: RamValue::Byte(0x2F4),
+                    weight: -1.0,
+                },
+            ],
+            terminations: vec![
+                Termination::AtLeast(RamValue::Byte(0x2F3), 9),
+                Termination::AtLeast(RamValue::Byte(0x2F4), 9),
+            ],
+            ..Self::new(ActionSet::from_keys(&[0x1, 0x4]))
+        }
+    }
+
+    /// BRIX by Andreas Gustafsson. The score is drawn from three BCD digits at 0x314, and the
+    /// game is won at 96. Lives are only kept in a register, so losing ends through `max_frames`.
+    pub fn brix() -> Self {
+        let score = RamValue::Bcd {
+            address: 0x314,
+            digits: 3,
+        };
+        Self {
+            rewards: vec![Reward {
+                value: score,
+                weight: 1.0,
+            }],
+            terminations: vec![Termination::AtLeast(score, 96)],
+            max_frames: Some(60 * 60 * 5),
+            ..Self::new(ActionSet::from_keys(&[0x4, 0x6]))
+        }
+    }
+}
+
+/// What an environment returns for each step.
+pub type Step = (Vec<u8>, f32, bool);
+
+/// A single game, reset to the moment after the ROM was loaded at the start of each episode.
+#[derive(Debug)]
+pub struct Env {
+    chip8: Chip8,
+    config: EnvConfig,
+    initial: Vec<u8>,
+    scores: Vec<u32>,
+    frames: u32,
+    done: bool,
+}
+
+impl Env {
+    pub fn new(platform: Platform, rom: &[u8], config: EnvConfig) -> Result<Self, RomError> {
+        let mut chip8 = Chip8::new(platform);
+        chip8.load_rom_bytes(rom)?;
+
+        Ok(Self {
+            initial: chip8.save_state(),
+            scores: Self::scores(&chip8, &config),
+            chip8,
+            config,
+            frames: 0,
+            done: false,
+        })
+    }
+
+    /// Starts a new episode and returns the first observation.
+    pub fn reset(&mut self) -> Vec<u8> {
+        self.chip8
+            .load_state(&self.initial)
+            .expect("snapshots taken by the environment are valid");
+        self.chip8.reset_keys();
+        self.scores = Self::scores(&self.chip8, &self.config);
+        self.frames = 0;
+        self.done = false;
+
+        self.observation()
+    }
+
+    /// Holds the keys of `action` for `frame_skip` frames, returning the observation after them,
+    /// the summed reward and whether the episode has ended. The episode also ends when the
+    /// program finishes or fails.
+    pub fn step(&mut self, action: usize) -> Step {
+        let keys = self.config.actions.keys(action);
+        let mut reward = 0.0;
+
+        for _ in 0..self.config.frame_skip.max(1) {
+            if self.done {
+                break;
+            }
+
+            self.chip8.reset_keys();
+            for key in (0..16).filter(|key| keys & (1 << key) != 0) {
+                self.chip8.key_down(key);
+            }
+            let failed = self.chip8.run_frame().is_err();
+            self.frames += 1;
+
+            let scores = Self::scores(&self.chip8, &self.config);
+            for ((rule, &old), &new) in self.config.rewards.iter().zip(&self.scores).zip(&scores) {
+                reward += rule.weight * (f64::from(new) - f64::from(old)) as f32;
+            }
+            self.scores = scores;
+
+            let ram = self.chip8.ram();
+            self.done = failed
+                || self.chip8.state != State::Running
+                || self.config.max_frames.is_some_and(|max| self.frames >= max)
+                || self
+                    .config
+                    .terminations
+                    .iter()
+                    .any(|rule| rule.reached(ram));
+        }
+
+        (self.observation(), reward, self.done)
+    }
+
+    /// The display row by row, one byte per pixel that is 1 where lit.
+    pub fn observation(&self) -> Vec<u8> {
+        self.chip8.framebuffer().to_vec()
+    }
+
+    /// Height and width of each observation.
+    pub fn observation_shape(&self) -> (usize, usize) {
+        let platform = &self.chip8.platform;
+        (
+            usize::from(platform.video_height),
+            usize::from(platform.video_width),
+        )
+    }
+
+    pub fn actions(&self) -> &ActionSet {
+        &self.config.actions
+    }
+
+    pub fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    fn scores(chip8: &Chip8, config: &EnvConfig) -> Vec<u32> {
+        config
+            .rewards
+            .iter()
+            .map(|rule| rule.value.read(chip8.ram()))
+            .collect()
+    }
+}
+
+/// Several environments stepped together across threads. An environment whose episode ends is
+/// reset straight away, and the first observation of its new episode is returned in place of the
+/// last one of the old.
+#[derive(Debug)]
+pub struct VecEnv {
+    envs: Vec<Env>,
+}
+
+impl VecEnv {
+    pub fn new(envs: Vec<Env>) -> Self {
+        Self { envs }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn envs(&self) -> &[Env] {
+        &self.envs
+    }
+
+    pub fn reset(&mut self) -> Vec<Vec<u8>> {
+        self.envs.iter_mut().map(Env::reset).collect()
+    }
+
+    /// Steps every environment with its action from `actions`.
+    pub fn step(&mut self, actions: &[usize]) -> Vec<Step> {
+        assert_eq!(self.envs.len(), actions.len(), "one action per environment");
+
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        let chunk = self.envs.len().div_ceil(threads).max(1);
+        let mut steps = vec![(Vec::new(), 0.0, false); self.envs.len()];
+
+        thread::scope(|scope| {
+            for ((envs, actions), steps) in self
+                .envs
+                .chunks_mut(chunk)
+                .zip(actions.chunks(chunk))
+                .zip(steps.chunks_mut(chunk))
+            {
+                scope.spawn(move || {
+                    for ((env, &action), step) in envs.iter_mut().zip(actions).zip(steps) {
+                        *step = env.step(action);
+                        if step.2 {
+                            step.0 = env.reset();
+                        }
+                    }
+                });
+            }
+        });
+
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Target;
+
+    // Counts up in 0x300 about once per frame, storing it as BCD at 0x310, until it reaches 250.
+    const COUNTER: [u8; 22] = [
+        0x60, 0x00, // LD V0, 0
+        0x70, 0x01, // ADD V0, 1
+        0xA3, 0x00, // LD I, 0x300
+        0xF0, 0x55, // LD [I], V0
+        0xA3, 0x10, // LD I, 0x310
+        0xF0, 0x33, // LD B, V0
+        0x61, 0x01, // LD V1, 1
+        0xF1, 0x15, // LD DT, V1
+        0xF1, 0x07, // LD V1, DT
+        0x31, 0x00, // SE V1, 0
+        0x12, 0x10, // JP 0x210
+    ];
+
+    fn counter_env(config: EnvConfig) -> Env {
+        let mut rom = COUNTER.to_vec();
+        rom.extend_from_slice(&[0x30, 0xFA, 0x12, 0x02]); // SE V0, 250; JP 0x202
+        Env::new(Platform::new(Target::Modern), &rom, config).unwrap()
+    }
+
+    #[test]
+    fn test_ram_value() {
+        let mut ram = [0u8; 4096];
+        ram[0x10..0x13].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(1, RamValue::Byte(0x10).read(&ram));
+        let bcd = RamValue::Bcd {
+            address: 0x10,
+            digits: 3,
+        };
+        assert_eq!(123, bcd.read(&ram));
+
+        ram[0xFFF] = 4;
+        ram[0] = 5;
+        let wrapped = RamValue::Bcd {
+            address: 0xFFFF,
+            digits: 2,
+        };
+        assert_eq!(45, wrapped.read(&ram));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut config = EnvConfig::new(ActionSet::full());
+        config.rewards.push(Reward {
+            value: RamValue::Byte(0x300),
+            weight: 0.5,
+        });
+        config.terminations.push(Termination::AtLeast(
+            RamValue::Bcd {
+                address: 0x310,
+                digits: 3,
+            },
+            20,
+        ));
+        let mut env = counter_env(config);
+        assert_eq!(17, env.actions().len());
+        assert_eq!(64 * 32, env.reset().len());
+
+        let (_, reward, done) = env.step(0);
+        assert_eq!(0.5 * f32::from(env.chip8().ram()[0x300]), reward);
+        assert!(reward > 0.0);
+        assert!(!done);
+
+        let mut total = reward;
+        loop {
+            let (_, reward, done) = env.step(3);
+            total += reward;
+            if done {
+                break;
+            }
+        }
+        assert_eq!(20, env.chip8().ram()[0x300]);
+        assert_eq!(10.0, total);
+
+        env.reset();
+        assert_eq!(0, env.chip8().ram()[0x300]);
+    }
+
+    #[test]
+    fn test_max_frames() {
+        let mut config = EnvConfig::new(ActionSet::from_keys(&[5]));
+        config.frame_skip = 1;
+        config.max_frames = Some(3);
+        let mut env = counter_env(config);
+        env.reset();
+        assert!(!env.step(1).2);
+        assert!(!env.step(1).2);
+        assert!(env.step(1).2);
+    }
+
+    #[test]
+    fn test_vec_env() {
+        let mut config = EnvConfig::new(ActionSet::full());
+        config.max_frames = Some(8);
+        let mut envs = VecEnv::new((0..3).map(|_| counter_env(config.clone())).collect());
+        assert_eq!(3, envs.reset().len());
+
+        let steps = envs.step(&[0, 1, 2]);
+        assert!(steps.iter().all(|(_, _, done)| !done));
+        let steps = envs.step(&[0, 1, 2]);
+        assert!(steps.iter().all(|(_, _, done)| *done));
+        assert!(envs.envs().iter().all(|env| env.chip8().ram()[0x300] == 0));
+    }
+}
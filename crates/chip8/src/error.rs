@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::MAX_ROM_SIZE;
+
 #[derive(Error, Debug)]
 pub enum ExecutionError {
     #[error("Invalid opcode {0:#06x}")]
@@ -7,4 +9,23 @@ pub enum ExecutionError {
 
     #[error("Memory access error {0:#06x}")]
     MemoryAccessError(u16),
+
+    #[error("Stack underflow at {0:#06x}")]
+    StackUnderflow(u16),
+
+    #[error("Stack overflow at {0:#06x}")]
+    StackOverflow(u16),
+}
+
+#[derive(Error, Debug)]
+pub enum RomError {
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("ROM is empty")]
+    Empty,
+
+    #[error("ROM is too big to fit in memory: {0} bytes, at most {MAX_ROM_SIZE} fit")]
+    TooLarge(usize),
 }
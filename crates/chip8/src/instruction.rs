@@ -0,0 +1,244 @@
+use core::fmt;
+
+use crate::error::ExecutionError;
+use crate::error::ExecutionError::InvalidOpcode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Cls,                    // 00E0
+    Ret,                    // 00EE
+    Exit,                   // 00FD
+    Jp(u16),                // 1NNN
+    Call(u16),              // 2NNN
+    SeByte(usize, u8),      // 3XKK
+    SneByte(usize, u8),     // 4XKK
+    SeReg(usize, usize),    // 5XY0
+    LdByte(usize, u8),      // 6XKK
+    AddByte(usize, u8),     // 7XKK
+    LdReg(usize, usize),    // 8XY0
+    Or(usize, usize),       // 8XY1
+    And(usize, usize),      // 8XY2
+    Xor(usize, usize),      // 8XY3
+    AddReg(usize, usize),   // 8XY4
+    Sub(usize, usize),      // 8XY5
+    Shr(usize, usize),      // 8XY6
+    Subn(usize, usize),     // 8XY7
+    Shl(usize, usize),      // 8XYE
+    SneReg(usize, usize),   // 9XY0
+    LdI(u16),               // ANNN
+    JpV0(u16),              // BNNN
+    Rnd(usize, u8),         // CXKK
+    Drw(usize, usize, u16), // DXYN
+    Skp(usize),             // EX9E
+    Sknp(usize),            // EXA1
+    LdVxDt(usize),          // FX07
+    LdVxK(usize),           // FX0A
+    LdDtVx(usize),          // FX15
+    LdStVx(usize),          // FX18
+    AddI(usize),            // FX1E
+    LdF(usize),             // FX29
+    LdB(usize),             // FX33
+    LdIVx(usize),           // FX55
+    LdVxI(usize),           // FX65
+}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Result<Self, ExecutionError> {
+        let c = (opcode & 0xF000) >> 12;
+        let x = ((opcode & 0x0F00) >> 8) as usize;
+        let y = ((opcode & 0x00F0) >> 4) as usize;
+        let d = opcode & 0x000F;
+
+        let nn = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+
+        let instruction = match (c, x, y, d) {
+            (0x0, 0x0, 0xE, 0x0) => Self::Cls,
+            (0x0, 0x0, 0xE, 0xE) => Self::Ret,
+            (0x0, 0x0, 0xF, 0xD) => Self::Exit,
+            (0x1, _, _, _) => Self::Jp(nnn),
+            (0x2, _, _, _) => Self::Call(nnn),
+            (0x3, _, _, _) => Self::SeByte(x, nn),
+            (0x4, _, _, _) => Self::SneByte(x, nn),
+            (0x5, _, _, 0x0) => Self::SeReg(x, y),
+            (0x6, _, _, _) => Self::LdByte(x, nn),
+            (0x7, _, _, _) => Self::AddByte(x, nn),
+            (0x8, _, _, 0x0) => Self::LdReg(x, y),
+            (0x8, _, _, 0x1) => Self::Or(x, y),
+            (0x8, _, _, 0x2) => Self::And(x, y),
+            (0x8, _, _, 0x3) => Self::Xor(x, y),
+            (0x8, _, _, 0x4) => Self::AddReg(x, y),
+            (0x8, _, _, 0x5) => Self::Sub(x, y),
+            (0x8, _, _, 0x6) => Self::Shr(x, y),
+            (0x8, _, _, 0x7) => Self::Subn(x, y),
+            (0x8, _, _, 0xE) => Self::Shl(x, y),
+            (0x9, _, _, 0x0) => Self::SneReg(x, y),
+            (0xA, _, _, _) => Self::LdI(nnn),
+            (0xB, _, _, _) => Self::JpV0(nnn),
+            (0xC, _, _, _) => Self::Rnd(x, nn),
+            (0xD, _, _, _) => Self::Drw(x, y, d),
+            (0xE, _, 0x9, 0xE) => Self::Skp(x),
+            (0xE, _, 0xA, 0x1) => Self::Sknp(x),
+            (0xF, _, 0x0, 0x7) => Self::LdVxDt(x),
+            (0xF, _, 0x0, 0xA) => Self::LdVxK(x),
+            (0xF, _, 0x1, 0x5) => Self::LdDtVx(x),
+            (0xF, _, 0x1, 0x8) => Self::LdStVx(x),
+            (0xF, _, 0x1, 0xE) => Self::AddI(x),
+            (0xF, _, 0x2, 0x9) => Self::LdF(x),
+            (0xF, _, 0x3, 0x3) => Self::LdB(x),
+            (0xF, _, 0x5, 0x5) => Self::LdIVx(x),
+            (0xF, _, 0x6, 0x5) => Self::LdVxI(x),
+            _ => {
+                return Err(InvalidOpcode(opcode));
+            }
+        };
+
+        Ok(instruction)
+    }
+
+    /// Whether the instruction conditionally skips the one following it.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SeByte(..)
+                | Self::SneByte(..)
+                | Self::SeReg(..)
+                | Self::SneReg(..)
+                | Self::Skp(_)
+                | Self::Sknp(_)
+        )
+    }
+
+    /// Bitmask of the V registers the instruction reads, with bit N set for VN.
+    pub fn registers_read(&self) -> u16 {
+        match *self {
+            Self::SeByte(x, _)
+            | Self::SneByte(x, _)
+            | Self::AddByte(x, _)
+            | Self::Skp(x)
+            | Self::Sknp(x)
+            | Self::LdDtVx(x)
+            | Self::LdStVx(x)
+            | Self::AddI(x)
+            | Self::LdF(x)
+            | Self::LdB(x) => 1 << x,
+            Self::SeReg(x, y)
+            | Self::SneReg(x, y)
+            | Self::Or(x, y)
+            | Self::And(x, y)
+            | Self::Xor(x, y)
+            | Self::AddReg(x, y)
+            | Self::Sub(x, y)
+            | Self::Shr(x, y)
+            | Self::Subn(x, y)
+            | Self::Shl(x, y)
+            | Self::Drw(x, y, _) => 1 << x | 1 << y,
+            Self::LdReg(_, y) => 1 << y,
+            Self::JpV0(_) => 1,
+            Self::LdIVx(x) => u16::MAX >> (15 - x),
+            _ => 0,
+        }
+    }
+
+    /// Bitmask of the V registers the instruction writes, including VF for flag results.
+    pub fn registers_written(&self) -> u16 {
+        match *self {
+            Self::LdByte(x, _)
+            | Self::AddByte(x, _)
+            | Self::LdReg(x, _)
+            | Self::Rnd(x, _)
+            | Self::LdVxDt(x)
+            | Self::LdVxK(x) => 1 << x,
+            Self::Or(x, _)
+            | Self::And(x, _)
+            | Self::Xor(x, _)
+            | Self::AddReg(x, _)
+            | Self::Sub(x, _)
+            | Self::Shr(x, _)
+            | Self::Subn(x, _)
+            | Self::Shl(x, _) => 1 << x | 1 << 0xF,
+            Self::Drw(..) => 1 << 0xF,
+            Self::LdVxI(x) => u16::MAX >> (15 - x),
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Cls => write!(f, "CLS"),
+            Self::Ret => write!(f, "RET"),
+            Self::Exit => write!(f, "EXIT"),
+            Self::Jp(nnn) => write!(f, "JP {nnn:#05x}"),
+            Self::Call(nnn) => write!(f, "CALL {nnn:#05x}"),
+            Self::SeByte(x, nn) => write!(f, "SE V{x:X}, {nn:#04x}"),
+            Self::SneByte(x, nn) => write!(f, "SNE V{x:X}, {nn:#04x}"),
+            Self::SeReg(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+            Self::LdByte(x, nn) => write!(f, "LD V{x:X}, {nn:#04x}"),
+            Self::AddByte(x, nn) => write!(f, "ADD V{x:X}, {nn:#04x}"),
+            Self::LdReg(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+            Self::Or(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+            Self::And(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+            Self::Xor(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self::AddReg(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self::Sub(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self::Shr(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self::Subn(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self::Shl(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self::SneReg(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self::LdI(nnn) => write!(f, "LD I, {nnn:#05x}"),
+            Self::JpV0(nnn) => write!(f, "JP V0, {nnn:#05x}"),
+            Self::Rnd(x, nn) => write!(f, "RND V{x:X}, {nn:#04x}"),
+            Self::Drw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self::Skp(x) => write!(f, "SKP V{x:X}"),
+            Self::Sknp(x) => write!(f, "SKNP V{x:X}"),
+            Self::LdVxDt(x) => write!(f, "LD V{x:X}, DT"),
+            Self::LdVxK(x) => write!(f, "LD V{x:X}, K"),
+            Self::LdDtVx(x) => write!(f, "LD DT, V{x:X}"),
+            Self::LdStVx(x) => write!(f, "LD ST, V{x:X}"),
+            Self::AddI(x) => write!(f, "ADD I, V{x:X}"),
+            Self::LdF(x) => write!(f, "LD F, V{x:X}"),
+            Self::LdB(x) => write!(f, "LD B, V{x:X}"),
+            Self::LdIVx(x) => write!(f, "LD [I], V{x:X}"),
+            Self::LdVxI(x) => write!(f, "LD V{x:X}, [I]"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Instruction::Jp(0x228), Instruction::decode(0x1228).unwrap());
+        assert_eq!(
+            Instruction::Drw(0, 1, 5),
+            Instruction::decode(0xD015).unwrap()
+        );
+        assert_eq!(Instruction::Shl(2, 3), Instruction::decode(0x823E).unwrap());
+        assert_eq!(Instruction::Exit, Instruction::decode(0x00FD).unwrap());
+        assert!(Instruction::decode(0x823F).is_err());
+    }
+
+    #[test]
+    fn test_registers() {
+        assert_eq!(
+            0b0000_0000_0000_1100,
+            Instruction::Sub(2, 3).registers_read()
+        );
+        assert_eq!(
+            0b1000_0000_0000_0100,
+            Instruction::Sub(2, 3).registers_written()
+        );
+        assert_eq!(
+            0b0000_0000_0000_0111,
+            Instruction::LdIVx(2).registers_read()
+        );
+        assert_eq!(
+            0b1111_1111_1111_1111,
+            Instruction::LdVxI(0xF).registers_written()
+        );
+    }
+}
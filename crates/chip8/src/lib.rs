@@ -1,13 +1,37 @@
-use crate::platform::Platform;
-pub use chip8::Chip8;
-pub use state::State;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub use chip8::{Chip8, Registers};
+pub use analysis::Analysis;
+pub use coverage::{Coverage, CoverageReport};
+pub use error::{ExecutionError, RomError};
+pub use instruction::Instruction;
+pub use lint::{lint, LintReport};
+pub use platform::{Platform, Quirks, Target, UnknownTarget};
+pub use sanitizer::{Finding, Sanitizer};
+pub use snapshot::{SnapshotError, SNAPSHOT_SIZE};
+pub use state::{Halt, State};
 use virtual_machine::VirtualMachine;
 
+pub mod analysis;
+pub mod audio;
 pub mod chip8;
+pub mod coverage;
+pub mod decompiler;
+pub mod disassembler;
+#[cfg(feature = "std")]
+pub mod env;
 mod error;
+pub mod instruction;
+pub mod lint;
 mod platform;
+pub mod sanitizer;
+mod snapshot;
 pub mod state;
 mod virtual_machine;
 
 pub const CHAR_SIZE: u8 = 0x5;
 pub const PROGRAM_START_ADDRESS: u16 = 0x200;
+/// Largest ROM that fits in memory from `PROGRAM_START_ADDRESS`.
+pub const MAX_ROM_SIZE: usize = 0x1000 - PROGRAM_START_ADDRESS as usize;
@@ -0,0 +1,433 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::analysis::Analysis;
+use crate::coverage::{Access, Coverage};
+use crate::instruction::Instruction;
+use crate::platform::{Platform, Quirks};
+use crate::{VirtualMachine, PROGRAM_START_ADDRESS};
+
+/// Stack depth the original COSMAC VIP interpreter guaranteed room for.
+pub const PORTABLE_STACK_DEPTH: u8 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Warning {
+    /// 8XY6/8XYE with X != Y, which only shifts VY on some interpreters.
+    Shift,
+    /// BNNN, which jumps relative to VX instead of V0 on some interpreters.
+    Jump,
+    /// FX55/FX65 followed by a use of I, which is only incremented on some interpreters.
+    LoadStore,
+    /// VF read after 8XY1/8XY2/8XY3, which only reset it on some interpreters.
+    VfReset,
+    /// A sprite drawn past the edge of the screen, which wraps on some interpreters.
+    Wrap,
+    /// VF used as an operand of an arithmetic or logic op that also writes the flag.
+    VfOperand,
+    /// A store through I into the interpreter and font area below 0x200.
+    FontWrite(u16),
+    /// A store into bytes that are executed, or execution of bytes that were stored.
+    SelfModifying(u16),
+    /// Subroutine nesting deeper than `PORTABLE_STACK_DEPTH`.
+    StackDepth(u8),
+}
+
+impl Warning {
+    /// The quirk whose setting changes the behaviour of the flagged code, if any.
+    pub fn quirk(&self) -> Option<Quirks> {
+        match self {
+            Self::Shift => Some(Quirks::SHIFT),
+            Self::Jump => Some(Quirks::JUMP),
+            Self::LoadStore => Some(Quirks::LOAD_STORE_INC_I),
+            Self::VfReset => Some(Quirks::VF_RESET),
+            Self::Wrap => Some(Quirks::WRAP),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shift => write!(f, "shift with X != Y depends on the SHIFT quirk"),
+            Self::Jump => write!(f, "jump with offset depends on the JUMP quirk"),
+            Self::LoadStore => {
+                write!(
+                    f,
+                    "I used after a load/store depends on the LOAD_STORE_INC_I quirk"
+                )
+            }
+            Self::VfReset => write!(f, "VF read after a logic op depends on the VF_RESET quirk"),
+            Self::Wrap => write!(
+                f,
+                "sprite drawn past the screen edge depends on the WRAP quirk"
+            ),
+            Self::VfOperand => write!(f, "VF used as an operand is overwritten by the flag"),
+            Self::FontWrite(addr) => write!(f, "write into the font area at {addr:#05x}"),
+            Self::SelfModifying(addr) => write!(f, "self-modifying code at {addr:#05x}"),
+            Self::StackDepth(depth) => write!(
+                f,
+                "stack depth {depth} exceeds the portable limit of {PORTABLE_STACK_DEPTH}"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Lint {
+    pub addr: u16,
+    pub warning: Warning,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}: {}", self.addr, self.warning)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintReport {
+    pub lints: Vec<Lint>,
+    /// Every quirk the ROM was seen to depend on.
+    pub quirks: Quirks,
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for lint in &self.lints {
+            writeln!(f, "{lint}")?;
+        }
+
+        let quirks: Vec<&str> = self.quirks.iter_names().map(|(name, _)| name).collect();
+        if quirks.is_empty() {
+            writeln!(f, "Depends on no quirks")
+        } else {
+            writeln!(f, "Depends on quirks: {}", quirks.join(", "))
+        }
+    }
+}
+
+/// Lints a ROM by analysing its reachable code and then running it for `cycles` instructions
+/// on `platform`, pressing pseudo-random keys so input loops make progress.
+pub fn lint(rom: &[u8], platform: &Platform, cycles: usize) -> LintReport {
+    let mut lints = BTreeSet::new();
+
+    lint_static(&Analysis::new(rom), &mut lints);
+    lint_dynamic(rom, platform, cycles, &mut lints);
+
+    let lints: Vec<Lint> = lints.into_iter().collect();
+    let quirks = lints
+        .iter()
+        .filter_map(|lint| lint.warning.quirk())
+        .fold(Quirks::empty(), |quirks, quirk| quirks | quirk);
+
+    LintReport { lints, quirks }
+}
+
+fn lint_static(analysis: &Analysis, lints: &mut BTreeSet<Lint>) {
+    for block in analysis.blocks.values() {
+        for (n, &(addr, instruction)) in block.instructions.iter().enumerate() {
+            let rest = &block.instructions[n + 1..];
+            let mut push = |warning| {
+                lints.insert(Lint { addr, warning });
+            };
+
+            match instruction {
+                Instruction::Shr(x, y) | Instruction::Shl(x, y) if x != y => push(Warning::Shift),
+                Instruction::JpV0(_) => push(Warning::Jump),
+                Instruction::LdIVx(_) | Instruction::LdVxI(_) if uses_i(rest) => {
+                    push(Warning::LoadStore)
+                }
+                Instruction::Or(..) | Instruction::And(..) | Instruction::Xor(..)
+                    if reads_vf(rest) =>
+                {
+                    push(Warning::VfReset)
+                }
+                Instruction::LdI(nnn) if nnn < PROGRAM_START_ADDRESS && stores(rest) => {
+                    push(Warning::FontWrite(nnn))
+                }
+                _ => {}
+            }
+
+            let is_alu = matches!(
+                instruction,
+                Instruction::Or(..)
+                    | Instruction::And(..)
+                    | Instruction::Xor(..)
+                    | Instruction::AddReg(..)
+                    | Instruction::Sub(..)
+                    | Instruction::Shr(..)
+                    | Instruction::Subn(..)
+                    | Instruction::Shl(..)
+            );
+            if is_alu && instruction.registers_read() & 1 << 0xF != 0 {
+                push(Warning::VfOperand);
+            }
+        }
+    }
+}
+
+/// Whether I is used before being reloaded.
+fn uses_i(rest: &[(u16, Instruction)]) -> bool {
+    for (_, instruction) in rest {
+        match instruction {
+            Instruction::LdI(_) | Instruction::LdF(_) => return false,
+            Instruction::Drw(..)
+            | Instruction::AddI(_)
+            | Instruction::LdB(_)
+            | Instruction::LdIVx(_)
+            | Instruction::LdVxI(_) => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Whether VF is read before being overwritten.
+fn reads_vf(rest: &[(u16, Instruction)]) -> bool {
+    for (_, instruction) in rest {
+        if instruction.registers_read() & 1 << 0xF != 0 {
+            return true;
+        }
+        if instruction.registers_written() & 1 << 0xF != 0 {
+            return false;
+        }
+    }
+
+    false
+}
+
+/// Whether something is stored through I before it is reloaded.
+fn stores(rest: &[(u16, Instruction)]) -> bool {
+    for (_, instruction) in rest {
+        match instruction {
+            Instruction::LdI(_) | Instruction::LdF(_) | Instruction::AddI(_) => return false,
+            Instruction::LdB(_) | Instruction::LdIVx(_) => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+fn lint_dynamic(rom: &[u8], platform: &Platform, cycles: usize, lints: &mut BTreeSet<Lint>) {
+    let mut vm = VirtualMachine::new();
+    vm.rng.seed(0xC8);
+    let mut coverage = Coverage::new();
+    let mut rng = fastrand::Rng::with_seed(0xC8);
+    let mut deepest = None;
+
+    let start = PROGRAM_START_ADDRESS as usize;
+    let len = rom.len().min(vm.ram.len() - start);
+    vm.ram[start..start + len].copy_from_slice(&rom[..len]);
+
+    for cycle in 0..cycles {
+        let pc = vm.pc;
+        if pc as usize + 1 >= vm.ram.len() {
+            break;
+        }
+
+        let opcode = u16::from(vm.ram[pc as usize]) << 8 | u16::from(vm.ram[pc as usize + 1]);
+        let Ok(instruction) = Instruction::decode(opcode) else {
+            break;
+        };
+        let mut push = |warning| {
+            lints.insert(Lint { addr: pc, warning });
+        };
+
+        if (coverage.access(pc) | coverage.access(pc + 1)).contains(Access::WRITTEN) {
+            push(Warning::SelfModifying(pc));
+        }
+        coverage.record_execute(pc);
+
+        if cycle % 32 == 0 {
+            vm.keypad = [false; 16];
+            vm.keypad[rng.usize(..16)] = true;
+        }
+
+        let stored = match instruction {
+            Instruction::LdB(_) => Some(vm.i..vm.i + 3),
+            Instruction::LdIVx(x) => Some(vm.i..vm.i + x as u16 + 1),
+            _ => None,
+        };
+        if let Some(stored) = stored.clone() {
+            if stored.end as usize > vm.ram.len() {
+                break;
+            }
+            for addr in stored {
+                if addr < PROGRAM_START_ADDRESS {
+                    push(Warning::FontWrite(addr));
+                }
+                if coverage.access(addr).contains(Access::EXECUTED) {
+                    push(Warning::SelfModifying(addr));
+                }
+            }
+        }
+
+        match instruction {
+            Instruction::Drw(x, y, n) => {
+                let vx = u16::from(vm.registers[x]) % platform.video_width;
+                let vy = u16::from(vm.registers[y]) % platform.video_height;
+                if vm.i as usize + n as usize > vm.ram.len() {
+                    break;
+                }
+
+                let clipped = (0..n).any(|row| {
+                    let pixels = vm.ram[(vm.i + row) as usize];
+                    let past_right = (0..8)
+                        .any(|col| pixels & (0x80 >> col) != 0 && vx + col >= platform.video_width);
+                    pixels != 0 && vy + row >= platform.video_height || past_right
+                });
+                if clipped {
+                    push(Warning::Wrap);
+                }
+            }
+            Instruction::LdVxI(x) if vm.i as usize + x >= vm.ram.len() => break,
+            Instruction::Call(_) if vm.sp as usize >= vm.stack.len() => {
+                deepest = Some((pc, vm.sp + 1));
+                break;
+            }
+            Instruction::Ret if vm.sp == 0 => break,
+            Instruction::Exit => break,
+            Instruction::Skp(x) | Instruction::Sknp(x) if vm.registers[x] > 0xF => break,
+            _ => {}
+        }
+
+        if vm.execute(opcode, platform).is_err() {
+            break;
+        }
+
+        if let Some(stored) = stored {
+            for addr in stored {
+                coverage.record_write(addr);
+            }
+        }
+
+        if vm.sp > deepest.map_or(PORTABLE_STACK_DEPTH, |(_, depth)| depth) {
+            deepest = Some((pc, vm.sp));
+        }
+
+        if (cycle + 1) % usize::from(platform.tick_rate.max(1)) == 0 {
+            vm.tick_timers();
+        }
+    }
+
+    if let Some((addr, depth)) = deepest {
+        lints.insert(Lint {
+            addr,
+            warning: Warning::StackDepth(depth),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(rom: &[u8]) -> Vec<Warning> {
+        lint(rom, &Platform::default(), 1000)
+            .lints
+            .iter()
+            .map(|lint| lint.warning)
+            .collect()
+    }
+
+    #[test]
+    fn test_quirks() {
+        // 0x200: SHR V1, V2
+        // 0x202: LD [I], V0
+        // 0x204: DRW V0, V0, 1
+        // 0x206: OR V0, V1
+        // 0x208: SE VF, 0
+        // 0x20A: JP V0, 0x20C
+        // 0x20C: JP 0x20C
+        let rom = [
+            0x81, 0x26, 0xF0, 0x55, 0xD0, 0x01, 0x80, 0x11, 0x3F, 0x00, 0xB2, 0x0C, 0x12, 0x0C,
+        ];
+
+        let report = lint(&rom, &Platform::default(), 1000);
+
+        assert_eq!(
+            Quirks::SHIFT | Quirks::JUMP | Quirks::LOAD_STORE_INC_I | Quirks::VF_RESET,
+            report.quirks
+        );
+        assert!(report
+            .to_string()
+            .ends_with("Depends on quirks: VF_RESET, LOAD_STORE_INC_I, SHIFT, JUMP\n"));
+    }
+
+    #[test]
+    fn test_wrap() {
+        // 0x200: LD V0, 62
+        // 0x202: DRW V0, V0, 1
+        // 0x204: JP 0x204
+        let rom = [0x60, 0x3E, 0xD0, 0x01, 0x12, 0x04];
+
+        assert_eq!(vec![Warning::Wrap], warnings(&rom));
+    }
+
+    #[test]
+    fn test_vf_operand() {
+        // 0x200: ADD V0, VF
+        // 0x202: JP 0x202
+        let rom = [0x80, 0xF4, 0x12, 0x02];
+
+        assert_eq!(vec![Warning::VfOperand], warnings(&rom));
+    }
+
+    #[test]
+    fn test_font_write() {
+        // 0x200: LD I, 0x010
+        // 0x202: LD B, V0
+        // 0x204: JP 0x204
+        let rom = [0xA0, 0x10, 0xF0, 0x33, 0x12, 0x04];
+
+        let warnings = warnings(&rom);
+
+        assert!(warnings.contains(&Warning::FontWrite(0x010)));
+        assert!(warnings.contains(&Warning::FontWrite(0x012)));
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // 0x200: LD I, 0x208
+        // 0x202: LD V0, 0x12
+        // 0x204: LD V1, 0x08
+        // 0x206: LD [I], V1
+        // 0x208: CLS, replaced by JP 0x208
+        let rom = [0xA2, 0x08, 0x60, 0x12, 0x61, 0x08, 0xF1, 0x55, 0x00, 0xE0];
+
+        assert!(warnings(&rom).contains(&Warning::SelfModifying(0x208)));
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        // 0x200: CALL 0x200
+        let rom = [0x22, 0x00];
+
+        let warnings = warnings(&rom);
+
+        assert_eq!(vec![Warning::StackDepth(17)], warnings);
+    }
+
+    #[test]
+    fn test_pong() {
+        let report = lint(
+            include_bytes!("../../../roms/PONG"),
+            &Platform::default(),
+            100_000,
+        );
+
+        // The paddles' Y is masked to 0..=31, so the random keys held while linting move the right
+        // paddle far enough down for its 6 rows to wrap to the top, where it is erased and drawn.
+        let lints: Vec<_> = report
+            .lints
+            .iter()
+            .map(|lint| (lint.addr, lint.warning))
+            .collect();
+        assert_eq!(vec![(0x22E, Warning::Wrap), (0x252, Warning::Wrap)], lints);
+        assert_eq!(Quirks::WRAP, report.quirks);
+    }
+}
@@ -1,7 +1,11 @@
+use alloc::string::{String, ToString};
 use bitflags::bitflags;
+use core::fmt;
+use core::str::FromStr;
+use thiserror::Error;
 
 bitflags! {
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Quirks: u8 {
         const VF_RESET = 0b0000_0001;  // VF reset after logic ops
         const LOAD_STORE_INC_I = 0b0000_0010;  // True: Do not increment I after load/store
@@ -12,7 +16,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Target {
     CosmacVIP,
     Modern,
@@ -21,6 +25,48 @@ pub enum Target {
     XoChip,
 }
 
+impl Target {
+    pub const ALL: [Target; 5] = [
+        Target::CosmacVIP,
+        Target::Modern,
+        Target::Chip48,
+        Target::SuperChip,
+        Target::XoChip,
+    ];
+
+    /// Short lowercase name used on the command line and in settings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::CosmacVIP => "cosmac-vip",
+            Target::Modern => "modern",
+            Target::Chip48 => "chip-48",
+            Target::SuperChip => "super-chip",
+            Target::XoChip => "xo-chip",
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("Unknown target {0:?}, expected one of cosmac-vip, modern, chip-48, super-chip, xo-chip")]
+pub struct UnknownTarget(pub String);
+
+impl FromStr for Target {
+    type Err = UnknownTarget;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Target::ALL
+            .into_iter()
+            .find(|target| target.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownTarget(name.to_string()))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Platform {
     pub target: Target,
@@ -71,11 +117,27 @@ impl Platform {
         }
     }
 
-    pub fn default() -> Self {
+    pub fn has_quirk(&self, quirk: Quirks) -> bool {
+        self.quirks.contains(quirk)
+    }
+}
+
+impl Default for Platform {
+    fn default() -> Self {
         Self::new(Target::CosmacVIP)
     }
+}
 
-    pub fn has_quirk(&self, quirk: Quirks) -> bool {
-        self.quirks.contains(quirk)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_names() {
+        for target in Target::ALL {
+            assert_eq!(Ok(target), target.to_string().parse());
+        }
+        assert_eq!(Ok(Target::SuperChip), "SUPER-CHIP".parse());
+        assert_eq!(Err(UnknownTarget("schip".to_string())), "schip".parse::<Target>());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,213 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::PROGRAM_START_ADDRESS;
+
+/// Byte written to every address the program did not load, chosen so that executing it is an
+/// invalid opcode and drawing it is obvious on screen.
+pub const POISON: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Finding {
+    /// `addr` was read through I before anything was written to it.
+    UninitializedRead { pc: u16, addr: u16 },
+    /// The instruction at `pc` was fetched from bytes that were uninitialized, or were read or
+    /// written as data.
+    ExecuteData { pc: u16 },
+    /// `addr` in the interpreter and font area below 0x200 was written through I.
+    InterpreterWrite { pc: u16, addr: u16 },
+    /// RET at `pc` with an empty stack.
+    StackUnderflow { pc: u16 },
+    /// The program finished with `depth` subroutine calls still on the stack.
+    StackImbalance { depth: u8 },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UninitializedRead { pc, addr } => {
+                write!(f, "{pc:#06x}: read of uninitialized memory at {addr:#05x}")
+            }
+            Self::ExecuteData { pc } => write!(f, "{pc:#06x}: execution from a data region"),
+            Self::InterpreterWrite { pc, addr } => {
+                write!(f, "{pc:#06x}: write to the interpreter area at {addr:#05x}")
+            }
+            Self::StackUnderflow { pc } => write!(f, "{pc:#06x}: return with an empty stack"),
+            Self::StackImbalance { depth } => {
+                write!(f, "exited with {depth} calls left on the stack")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    initialized: [bool; 4096],
+    data: [bool; 4096],
+    findings: Vec<Finding>,
+    seen: BTreeSet<Finding>,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Self {
+            initialized: [false; 4096],
+            data: [false; 4096],
+            findings: Vec::new(),
+            seen: BTreeSet::new(),
+        }
+    }
+
+    /// Marks `loaded` and the font as initialized and fills the rest of `ram` with `POISON`.
+    pub fn poison(&mut self, ram: &mut [u8], font: Range<usize>, loaded: Range<usize>) {
+        for (addr, byte) in ram.iter_mut().enumerate() {
+            let is_loaded = font.contains(&addr) || loaded.contains(&addr);
+            self.initialized[addr] = is_loaded;
+            if !is_loaded {
+                *byte = POISON;
+            }
+        }
+    }
+
+    pub fn fetch(&mut self, pc: u16) {
+        let bytes = [pc as usize % 4096, (pc as usize + 1) % 4096];
+        if bytes
+            .iter()
+            .any(|&addr| !self.initialized[addr] || self.data[addr])
+        {
+            self.report(Finding::ExecuteData { pc });
+        }
+    }
+
+    pub fn read(&mut self, pc: u16, addr: u16) {
+        let addr = addr as usize % 4096;
+        self.data[addr] = true;
+        if !self.initialized[addr] {
+            self.report(Finding::UninitializedRead {
+                pc,
+                addr: addr as u16,
+            });
+        }
+    }
+
+    pub fn write(&mut self, pc: u16, addr: u16) {
+        let addr = addr as usize % 4096;
+        self.data[addr] = true;
+        self.initialized[addr] = true;
+        if addr < PROGRAM_START_ADDRESS as usize {
+            self.report(Finding::InterpreterWrite {
+                pc,
+                addr: addr as u16,
+            });
+        }
+    }
+
+    pub fn ret(&mut self, pc: u16, sp: u8) {
+        if sp == 0 {
+            self.report(Finding::StackUnderflow { pc });
+        }
+    }
+
+    pub fn exit(&mut self, sp: u8) {
+        if sp != 0 {
+            self.report(Finding::StackImbalance { depth: sp });
+        }
+    }
+
+    /// Findings in the order they were first seen, each reported once.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    fn report(&mut self, finding: Finding) {
+        if self.seen.insert(finding) {
+            self.findings.push(finding);
+        }
+    }
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poison() {
+        let mut sanitizer = Sanitizer::new();
+        let mut ram = [0; 4096];
+
+        sanitizer.poison(&mut ram, 0..80, 0x200..0x204);
+
+        assert_eq!(0, ram[0]);
+        assert_eq!(POISON, ram[80]);
+        assert_eq!(0, ram[0x203]);
+        assert_eq!(POISON, ram[0x204]);
+    }
+
+    #[test]
+    fn test_uninitialized_read() {
+        let mut sanitizer = Sanitizer::new();
+        sanitizer.poison(&mut [0; 4096], 0..80, 0x200..0x204);
+
+        sanitizer.read(0x200, 0x300);
+        sanitizer.read(0x200, 0x300);
+        sanitizer.write(0x202, 0x301);
+        sanitizer.read(0x202, 0x301);
+
+        assert_eq!(
+            &[Finding::UninitializedRead {
+                pc: 0x200,
+                addr: 0x300
+            }],
+            sanitizer.findings()
+        );
+    }
+
+    #[test]
+    fn test_execute_data() {
+        let mut sanitizer = Sanitizer::new();
+        sanitizer.poison(&mut [0; 4096], 0..80, 0x200..0x206);
+
+        sanitizer.fetch(0x200);
+        sanitizer.read(0x200, 0x204);
+        sanitizer.fetch(0x204);
+        sanitizer.fetch(0x206);
+
+        assert_eq!(
+            &[
+                Finding::ExecuteData { pc: 0x204 },
+                Finding::ExecuteData { pc: 0x206 }
+            ],
+            sanitizer.findings()
+        );
+    }
+
+    #[test]
+    fn test_interpreter_write_and_stack() {
+        let mut sanitizer = Sanitizer::new();
+
+        sanitizer.write(0x200, 0x1FF);
+        sanitizer.write(0x200, 0x200);
+        sanitizer.ret(0x202, 0);
+        sanitizer.exit(2);
+
+        assert_eq!(
+            &[
+                Finding::InterpreterWrite {
+                    pc: 0x200,
+                    addr: 0x1FF
+                },
+                Finding::StackUnderflow { pc: 0x202 },
+                Finding::StackImbalance { depth: 2 }
+            ],
+            sanitizer.findings()
+        );
+    }
+}
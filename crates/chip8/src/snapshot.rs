@@ -0,0 +1,191 @@
+use alloc::vec::Vec;
+use thiserror::Error;
+
+use crate::virtual_machine::{VirtualMachine, VIDEO_SIZE};
+use crate::{State, MAX_ROM_SIZE};
+
+const MAGIC: &[u8; 4] = b"CH8S";
+const VERSION: u8 = 1;
+
+/// Size in bytes of every snapshot produced by `Chip8::save_state`.
+pub const SNAPSHOT_SIZE: usize =
+    MAGIC.len() + 1 + 1 + 2 + 2 + 2 + 3 + 16 + 16 * 2 + 4096 + VIDEO_SIZE;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SnapshotError {
+    #[error("Snapshot is {0} bytes, expected {SNAPSHOT_SIZE}")]
+    Size(usize),
+
+    #[error("Not a snapshot")]
+    Magic,
+
+    #[error("Unsupported snapshot version {0}")]
+    Version(u8),
+
+    #[error("Invalid snapshot state {0}")]
+    State(u8),
+
+    #[error("Snapshot stack pointer {0} is past the end of the stack")]
+    StackPointer(u8),
+
+    #[error("Snapshot program size {0} is larger than {MAX_ROM_SIZE} bytes")]
+    ProgramSize(u16),
+
+    #[error("Snapshot address {0:#06x} is outside memory")]
+    Address(u16),
+}
+
+pub(crate) fn encode(vm: &VirtualMachine, state: State, program_size: u16) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SNAPSHOT_SIZE);
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.push(match state {
+        State::Running => 0,
+        State::Paused => 1,
+        State::Finished => 2,
+        State::Off => 3,
+    });
+    bytes.extend_from_slice(&program_size.to_le_bytes());
+    bytes.extend_from_slice(&vm.pc.to_le_bytes());
+    bytes.extend_from_slice(&vm.i.to_le_bytes());
+    bytes.extend_from_slice(&[vm.sp, vm.dt, vm.st]);
+    bytes.extend_from_slice(&vm.registers);
+    for address in vm.stack {
+        bytes.extend_from_slice(&address.to_le_bytes());
+    }
+    bytes.extend_from_slice(&vm.ram);
+    bytes.extend_from_slice(&vm.video);
+
+    bytes
+}
+
+/// Restores `vm` from `bytes`, returning the saved state and program size. `vm` is untouched if
+/// the snapshot is rejected.
+pub(crate) fn decode(bytes: &[u8], vm: &mut VirtualMachine) -> Result<(State, u16), SnapshotError> {
+    if bytes.len() != SNAPSHOT_SIZE {
+        return Err(SnapshotError::Size(bytes.len()));
+    }
+    let (magic, mut rest) = bytes.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(SnapshotError::Magic);
+    }
+
+    let mut take = |count: usize| {
+        let (taken, remaining) = rest.split_at(count);
+        rest = remaining;
+        taken
+    };
+    let u16_at = |bytes: &[u8]| u16::from_le_bytes([bytes[0], bytes[1]]);
+
+    let version = take(1)[0];
+    if version != VERSION {
+        return Err(SnapshotError::Version(version));
+    }
+    let state = match take(1)[0] {
+        0 => State::Running,
+        1 => State::Paused,
+        2 => State::Finished,
+        3 => State::Off,
+        state => return Err(SnapshotError::State(state)),
+    };
+    let program_size = u16_at(take(2));
+    if usize::from(program_size) > MAX_ROM_SIZE {
+        return Err(SnapshotError::ProgramSize(program_size));
+    }
+    let (pc, i) = (u16_at(take(2)), u16_at(take(2)));
+    if let Some(&address) = [pc, i]
+        .iter()
+        .find(|&&address| usize::from(address) >= vm.ram.len())
+    {
+        return Err(SnapshotError::Address(address));
+    }
+    let timers = take(3);
+    // A full stack has `sp` equal to its length; anything past that would index out of bounds.
+    if usize::from(timers[0]) > vm.stack.len() {
+        return Err(SnapshotError::StackPointer(timers[0]));
+    }
+
+    (vm.pc, vm.i) = (pc, i);
+    (vm.sp, vm.dt, vm.st) = (timers[0], timers[1], timers[2]);
+    vm.registers.copy_from_slice(take(16));
+    for (address, bytes) in vm.stack.iter_mut().zip(take(32).chunks(2)) {
+        *address = u16_at(bytes);
+    }
+    vm.ram.copy_from_slice(take(4096));
+    vm.video.copy_from_slice(take(VIDEO_SIZE));
+
+    Ok((state, program_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut vm = VirtualMachine::new();
+        vm.pc = 0x234;
+        vm.i = 0x345;
+        vm.sp = 2;
+        vm.st = 7;
+        vm.registers[0xF] = 1;
+        vm.stack[1] = 0x456;
+        vm.ram[0xFFF] = 0xAB;
+        vm.video[VIDEO_SIZE - 1] = 1;
+
+        let bytes = encode(&vm, State::Paused, 0x80);
+        let mut restored = VirtualMachine::new();
+
+        assert_eq!(SNAPSHOT_SIZE, bytes.len());
+        assert_eq!(Ok((State::Paused, 0x80)), decode(&bytes, &mut restored));
+        assert_eq!(
+            (vm.pc, vm.i, vm.sp, vm.st, vm.registers, vm.stack),
+            (
+                restored.pc,
+                restored.i,
+                restored.sp,
+                restored.st,
+                restored.registers,
+                restored.stack
+            )
+        );
+        assert_eq!(vm.ram, restored.ram);
+        assert_eq!(vm.video, restored.video);
+    }
+
+    #[test]
+    fn test_rejected() {
+        let mut vm = VirtualMachine::new();
+        let mut bytes = encode(&vm, State::Running, 0);
+
+        assert_eq!(Err(SnapshotError::Size(3)), decode(&bytes[..3], &mut vm));
+        bytes[5] = 4;
+        assert_eq!(Err(SnapshotError::State(4)), decode(&bytes, &mut vm));
+        bytes[5] = 0;
+        bytes[6..8].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert_eq!(
+            Err(SnapshotError::ProgramSize(0xFFFF)),
+            decode(&bytes, &mut vm)
+        );
+        bytes[6..8].copy_from_slice(&(MAX_ROM_SIZE as u16).to_le_bytes());
+        bytes[8..10].copy_from_slice(&0x1000u16.to_le_bytes());
+        assert_eq!(Err(SnapshotError::Address(0x1000)), decode(&bytes, &mut vm));
+        bytes[8..10].copy_from_slice(&0x200u16.to_le_bytes());
+        bytes[10..12].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert_eq!(Err(SnapshotError::Address(0xFFFF)), decode(&bytes, &mut vm));
+        bytes[10..12].copy_from_slice(&0xFFFu16.to_le_bytes());
+        bytes[12] = 17;
+        assert_eq!(
+            Err(SnapshotError::StackPointer(17)),
+            decode(&bytes, &mut vm)
+        );
+        assert_eq!(0, vm.sp);
+        bytes[12] = 16;
+        assert!(decode(&bytes, &mut vm).is_ok());
+        bytes[4] = 9;
+        assert_eq!(Err(SnapshotError::Version(9)), decode(&bytes, &mut vm));
+        bytes[0] = b'X';
+        assert_eq!(Err(SnapshotError::Magic), decode(&bytes, &mut vm));
+    }
+}
@@ -1,3 +1,5 @@
+use core::fmt;
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum State {
     Running,
@@ -5,3 +7,24 @@ pub enum State {
     Finished,
     Off,
 }
+
+/// Why a program stopped making progress.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Halt {
+    /// SUPER-CHIP's 00FD exit.
+    Exit,
+    /// A 1NNN jumping to its own address, which nothing can break out of.
+    SelfJump(u16),
+    /// FX0A at this address with both timers stopped, so only a key press changes anything.
+    WaitingForKey(u16),
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Halt::Exit => write!(f, "Exited"),
+            Halt::SelfJump(addr) => write!(f, "Jumped to itself at {addr:#05x}"),
+            Halt::WaitingForKey(addr) => write!(f, "Waiting for a key at {addr:#05x}"),
+        }
+    }
+}
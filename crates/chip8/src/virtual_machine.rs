@@ -1,10 +1,36 @@
+use crate::coverage::Coverage;
 use crate::error::ExecutionError;
-use crate::error::ExecutionError::InvalidOpcode;
+use crate::error::ExecutionError::{StackOverflow, StackUnderflow};
+use crate::instruction::Instruction;
 use crate::platform::{Platform, Quirks};
+use crate::sanitizer::Sanitizer;
 use crate::{CHAR_SIZE, PROGRAM_START_ADDRESS};
 use log::debug;
 
-const FONTS: [u8; 80] = [
+pub const FONTS_SIZE: usize = 80;
+
+/// Seed for CXKK without `std`, where there is no entropy to seed from.
+#[cfg(not(feature = "std"))]
+fn seed() -> u64 {
+    0xC8
+}
+
+/// A random seed for CXKK. `fastrand::Rng::new` would register a thread-local destructor through
+/// `thread::current`, which crashes hosts that unload the library, like libretro frontends.
+#[cfg(feature = "std")]
+fn seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+/// Pixels in the largest display, SUPER-CHIP's 128x64. Smaller displays use the start of the
+/// buffer with their own width as the stride.
+pub const VIDEO_SIZE: usize = 128 * 64;
+
+const FONTS: [u8; FONTS_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -27,7 +53,7 @@ const FONTS: [u8; 80] = [
 pub struct VirtualMachine {
     pub ram: [u8; 4096],
     pub keypad: [bool; 16],
-    pub video: [u8; 2048],
+    pub video: [u8; VIDEO_SIZE],
     pub i: u16,
     pub pc: u16,
     pub sp: u8,
@@ -35,6 +61,9 @@ pub struct VirtualMachine {
     pub st: u8,
     pub stack: [u16; 16],
     pub registers: [u8; 16],
+    pub coverage: Option<Coverage>,
+    pub sanitizer: Option<Sanitizer>,
+    pub rng: fastrand::Rng,
 }
 
 impl VirtualMachine {
@@ -45,7 +74,7 @@ impl VirtualMachine {
         Self {
             ram,
             keypad: [false; 16],
-            video: [0; 2048],
+            video: [0; VIDEO_SIZE],
             i: 0,
             pc: PROGRAM_START_ADDRESS,
             sp: 0,
@@ -53,60 +82,74 @@ impl VirtualMachine {
             st: 0,
             stack: [0; 16],
             registers: [0; 16],
+            coverage: None,
+            sanitizer: None,
+            rng: fastrand::Rng::with_seed(seed()),
         }
     }
 
-    pub fn rand_byte(&self) -> u8 {
-        fastrand::u8(..=u8::MAX)
+    pub fn rand_byte(&mut self) -> u8 {
+        self.rng.u8(..)
+    }
+
+    /// Decrements the delay and sound timers, called once per 60 Hz frame.
+    pub fn tick_timers(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
     }
 
     pub fn execute(&mut self, opcode: u16, platform: &Platform) -> Result<(), ExecutionError> {
-        let c = (opcode & 0xF000) >> 12;
-        let x = ((opcode & 0x0F00) >> 8) as usize;
-        let y = ((opcode & 0x00F0) >> 4) as usize;
-        let d = opcode & 0x000F;
-
-        let nn = (opcode & 0x00FF) as u8;
-        let nnn = opcode & 0x0FFF;
-
-        match (c, x, y, d) {
-            (0x0, 0x0, 0xE, 0x0) => self.op_00e0(),
-            (0x0, 0x0, 0xE, 0xE) => self.op_00ee(),
-            (0x1, _, _, _) => self.op_1nnn(nnn),
-            (0x2, _, _, _) => self.op_2nnn(nnn),
-            (0x3, _, _, _) => self.op_3xkk(x, nn),
-            (0x4, _, _, _) => self.op_4xkk(x, nn),
-            (0x5, _, _, 0x0) => self.op_5xy0(x, y),
-            (0x6, _, _, _) => self.op_6xkk(x, nn),
-            (0x7, _, _, _) => self.op_7xkk(x, nn),
-            (0x8, _, _, 0x0) => self.op_8xy0(x, y),
-            (0x8, _, _, 0x1) => self.op_8xy1(x, y),
-            (0x8, _, _, 0x2) => self.op_8xy2(x, y),
-            (0x8, _, _, 0x3) => self.op_8xy3(x, y),
-            (0x8, _, _, 0x4) => self.op_8xy4(x, y),
-            (0x8, _, _, 0x5) => self.op_8xy5(x, y),
-            (0x8, _, _, 0x6) => self.op_8xy6(platform, x, y),
-            (0x8, _, _, 0x7) => self.op_8xy7(x, y),
-            (0x8, _, _, 0xE) => self.op_8xye(platform, x, y),
-            (0x9, _, _, 0x0) => self.op_9xy0(x, y),
-            (0xA, _, _, _) => self.op_annn(nnn),
-            (0xB, _, _, _) => self.op_bnnn(nnn),
-            (0xC, _, _, _) => self.op_cxkk(x, nn),
-            (0xD, _, _, _) => self.op_dxyn(platform, x, y, d),
-            (0xE, _, 0x9, 0xE) => self.op_ex9e(x),
-            (0xE, _, 0xA, 0x1) => self.op_exa1(x),
-            (0xF, _, 0x0, 0x7) => self.op_fx07(x),
-            (0xF, _, 0x0, 0xA) => self.op_fx0a(x),
-            (0xF, _, 0x1, 0x5) => self.op_fx15(x),
-            (0xF, _, 0x1, 0x8) => self.op_fx18(x),
-            (0xF, _, 0x1, 0xE) => self.op_fx1e(x),
-            (0xF, _, 0x2, 0x9) => self.op_fx29(x),
-            (0xF, _, 0x3, 0x3) => self.op_fx33(x),
-            (0xF, _, 0x5, 0x5) => self.op_fx55(x),
-            (0xF, _, 0x6, 0x5) => self.op_fx65(x),
-            _ => {
-                return Err(InvalidOpcode(opcode));
+        let instruction = Instruction::decode(opcode)?;
+        match instruction {
+            Instruction::Ret if self.sp == 0 => {
+                if let Some(sanitizer) = &mut self.sanitizer {
+                    sanitizer.ret(self.pc, self.sp);
+                }
+                return Err(StackUnderflow(self.pc));
+            }
+            Instruction::Call(_) if self.sp as usize >= self.stack.len() => {
+                return Err(StackOverflow(self.pc));
             }
+            _ => {}
+        }
+
+        match instruction {
+            Instruction::Cls => self.op_00e0(),
+            Instruction::Ret => self.op_00ee(),
+            // Stopping the machine is up to `Chip8`, so PC stays on the instruction.
+            Instruction::Exit => {}
+            Instruction::Jp(nnn) => self.op_1nnn(nnn),
+            Instruction::Call(nnn) => self.op_2nnn(nnn),
+            Instruction::SeByte(x, nn) => self.op_3xkk(x, nn),
+            Instruction::SneByte(x, nn) => self.op_4xkk(x, nn),
+            Instruction::SeReg(x, y) => self.op_5xy0(x, y),
+            Instruction::LdByte(x, nn) => self.op_6xkk(x, nn),
+            Instruction::AddByte(x, nn) => self.op_7xkk(x, nn),
+            Instruction::LdReg(x, y) => self.op_8xy0(x, y),
+            Instruction::Or(x, y) => self.op_8xy1(x, y),
+            Instruction::And(x, y) => self.op_8xy2(x, y),
+            Instruction::Xor(x, y) => self.op_8xy3(x, y),
+            Instruction::AddReg(x, y) => self.op_8xy4(x, y),
+            Instruction::Sub(x, y) => self.op_8xy5(x, y),
+            Instruction::Shr(x, y) => self.op_8xy6(platform, x, y),
+            Instruction::Subn(x, y) => self.op_8xy7(x, y),
+            Instruction::Shl(x, y) => self.op_8xye(platform, x, y),
+            Instruction::SneReg(x, y) => self.op_9xy0(x, y),
+            Instruction::LdI(nnn) => self.op_annn(nnn),
+            Instruction::JpV0(nnn) => self.op_bnnn(nnn),
+            Instruction::Rnd(x, nn) => self.op_cxkk(x, nn),
+            Instruction::Drw(x, y, n) => self.op_dxyn(platform, x, y, n),
+            Instruction::Skp(x) => self.op_ex9e(x),
+            Instruction::Sknp(x) => self.op_exa1(x),
+            Instruction::LdVxDt(x) => self.op_fx07(x),
+            Instruction::LdVxK(x) => self.op_fx0a(x),
+            Instruction::LdDtVx(x) => self.op_fx15(x),
+            Instruction::LdStVx(x) => self.op_fx18(x),
+            Instruction::AddI(x) => self.op_fx1e(x),
+            Instruction::LdF(x) => self.op_fx29(x),
+            Instruction::LdB(x) => self.op_fx33(x),
+            Instruction::LdIVx(x) => self.op_fx55(x),
+            Instruction::LdVxI(x) => self.op_fx65(x),
         }
 
         Ok(())
@@ -116,6 +159,28 @@ impl VirtualMachine {
         self.pc += 2;
     }
 
+    fn read(&mut self, addr: u16) -> u8 {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_read(addr);
+        }
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.read(self.pc, addr);
+        }
+
+        self.ram[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_write(addr);
+        }
+        if let Some(sanitizer) = &mut self.sanitizer {
+            sanitizer.write(self.pc, addr);
+        }
+
+        self.ram[addr as usize] = value;
+    }
+
     fn op_00e0(&mut self) {
         debug!("00E0: CLS - Clear screen");
 
@@ -328,16 +393,14 @@ impl VirtualMachine {
         let vy = self.registers[y] as u16 % video_height;
 
         for display_y in 0..n {
-            let pixel = self.ram[(self.i + display_y) as usize];
+            let pixel = self.read(self.i + display_y);
 
             for display_x in 0..8 {
                 if pixel & (0x80 >> display_x) != 0 {
-                    let x_pos = vx + display_x;
-                    let y_pos = vy + display_y;
-
-                    if !platform.has_quirk(Quirks::WRAP) && x_pos >= video_width
-                        || y_pos >= video_height
-                    {
+                    let (mut x_pos, mut y_pos) = (vx + display_x, vy + display_y);
+                    if platform.has_quirk(Quirks::WRAP) {
+                        (x_pos, y_pos) = (x_pos % video_width, y_pos % video_height);
+                    } else if x_pos >= video_width || y_pos >= video_height {
                         continue;
                     }
 
@@ -471,7 +534,7 @@ impl VirtualMachine {
 
         let mut result = self.registers[x];
         for offset in (0..3).rev() {
-            self.ram[(self.i + offset) as usize] = result % 10;
+            self.write(self.i + offset, result % 10);
             result /= 10;
         }
 
@@ -482,7 +545,7 @@ impl VirtualMachine {
         debug!("FX55: LD [I], Vx - Store V0~Vx in memory starting at location I");
 
         for offset in 0..=x {
-            self.ram[self.i as usize + offset] = self.registers[offset];
+            self.write(self.i + offset as u16, self.registers[offset]);
         }
 
         self.sne();
@@ -492,7 +555,7 @@ impl VirtualMachine {
         debug!("FX65: LD Vx, [I] - Read registers V0~Vx from memory starting at location I");
 
         for offset in 0..=x {
-            self.registers[offset] = self.ram[self.i as usize + offset];
+            self.registers[offset] = self.read(self.i + offset as u16);
         }
 
         self.sne();
@@ -501,6 +564,8 @@ impl VirtualMachine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::coverage::Access;
+    use crate::platform::Target;
 
     #[test]
     fn test_00e0() {
@@ -816,13 +881,14 @@ mod tests {
     #[test]
     fn test_cxkk() {
         let mut vm = VirtualMachine::new();
+        vm.rng.seed(0xC8);
         let x = 0xD;
         let nn = 0xE;
         vm.registers[x] = nn;
 
         vm.op_cxkk(x, nn);
 
-        assert_ne!(nn, vm.registers[x]);
+        assert_eq!(fastrand::Rng::with_seed(0xC8).u8(..) & nn, vm.registers[x]);
     }
 
     #[test]
@@ -839,6 +905,34 @@ mod tests {
         assert_eq!(0x1, vm.registers[0xF]);
     }
 
+    #[test]
+    fn test_op_dxyn_edges() {
+        let mut platform = Platform::new(Target::SuperChip);
+        let mut vm = VirtualMachine::new();
+        vm.i = 0x200;
+        vm.ram[0x200..0x202].copy_from_slice(&[0xFF, 0xFF]);
+        (vm.registers[0], vm.registers[1]) = (124, 63);
+        let lit = |vm: &VirtualMachine| -> Vec<usize> {
+            (0..VIDEO_SIZE).filter(|&i| vm.video[i] != 0).collect()
+        };
+
+        // Clipped at the right and bottom edges, without spilling into the next row.
+        vm.op_dxyn(&platform, 0, 1, 2);
+        assert_eq!(vec![63 * 128 + 124, 8189, 8190, 8191], lit(&vm));
+
+        // Wrapped to the left and top edges.
+        vm.video = [0; VIDEO_SIZE];
+        platform.quirks |= Quirks::WRAP;
+        vm.op_dxyn(&platform, 0, 1, 2);
+        let corners = [0, 1, 2, 3, 124, 125, 126, 127];
+        let expected: Vec<usize> = corners
+            .iter()
+            .copied()
+            .chain(corners.iter().map(|x| 63 * 128 + x))
+            .collect();
+        assert_eq!(expected, lit(&vm));
+    }
+
     #[test]
     fn test_ex9e() {
         // Skip case
@@ -1009,4 +1103,39 @@ mod tests {
         assert_eq!(expected, vm.registers[1]);
         assert_eq!(expected, vm.registers[2]);
     }
+
+    #[test]
+    fn test_coverage() {
+        let mut vm = VirtualMachine::new();
+        vm.coverage = Some(Coverage::new());
+        vm.i = 0x300;
+        let platform = Platform::default();
+
+        vm.op_dxyn(&platform, 0, 0, 2);
+        vm.op_fx55(0);
+
+        let coverage = vm.coverage.unwrap();
+        assert!(coverage
+            .access(0x300)
+            .contains(Access::READ | Access::WRITTEN));
+        assert_eq!(Access::READ, coverage.access(0x301));
+        assert!(coverage.access(0x302).is_empty());
+    }
+
+    #[test]
+    fn test_stack_errors() {
+        let mut vm = VirtualMachine::new();
+        let platform = Platform::default();
+
+        assert!(matches!(
+            vm.execute(0x00EE, &platform),
+            Err(ExecutionError::StackUnderflow(PROGRAM_START_ADDRESS))
+        ));
+
+        vm.sp = 16;
+        assert!(matches!(
+            vm.execute(0x2300, &platform),
+            Err(ExecutionError::StackOverflow(PROGRAM_START_ADDRESS))
+        ));
+    }
 }
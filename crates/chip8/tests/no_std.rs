@@ -0,0 +1,24 @@
+//! Builds the crate without its default `std` feature, where the library is `no_std`.
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_no_std_build() {
+    // A separate target directory keeps this build from waiting on the lock held by the one
+    // running the tests.
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-std");
+    let output = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--no-default-features", "--manifest-path"])
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target_dir)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,184 @@
+use std::ffi::{c_uint, CStr, CString};
+use std::sync::OnceLock;
+
+use chip8::audio::Beeper;
+use chip8::{Chip8, ExecutionError, Platform, Quirks, RomError, Target};
+
+use crate::sys::*;
+
+/// Joypad button and description for each CHIP-8 key, indexed by key value. The d-pad sits on
+/// 5/7/8/9, the movement keys most SUPER-CHIP games use, and A/B on the 6/4 pair next to them.
+pub const JOYPAD: [(c_uint, &CStr); 16] = [
+    (RETRO_DEVICE_ID_JOYPAD_Y, c"Key 0"),
+    (RETRO_DEVICE_ID_JOYPAD_L, c"Key 1"),
+    (RETRO_DEVICE_ID_JOYPAD_X, c"Key 2"),
+    (RETRO_DEVICE_ID_JOYPAD_R, c"Key 3"),
+    (RETRO_DEVICE_ID_JOYPAD_B, c"Key 4"),
+    (RETRO_DEVICE_ID_JOYPAD_UP, c"Key 5"),
+    (RETRO_DEVICE_ID_JOYPAD_A, c"Key 6"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, c"Key 7"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, c"Key 8"),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, c"Key 9"),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, c"Key A"),
+    (RETRO_DEVICE_ID_JOYPAD_R3, c"Key B"),
+    (RETRO_DEVICE_ID_JOYPAD_L2, c"Key C"),
+    (RETRO_DEVICE_ID_JOYPAD_R2, c"Key D"),
+    (RETRO_DEVICE_ID_JOYPAD_L3, c"Key E"),
+    (RETRO_DEVICE_ID_JOYPAD_START, c"Key F"),
+];
+
+/// Keyboard keys for each CHIP-8 key, the same layout as the desktop frontend. libretro key codes
+/// for letters and digits are their lowercase ASCII values.
+pub const KEYBOARD: [u8; 16] = *b"x123qweasdzc4rfv";
+
+const LIT: u32 = 0x00FF_FFFF;
+
+/// Everything the core keeps between calls for a loaded game.
+#[derive(Debug)]
+pub struct Game {
+    pub chip8: Chip8,
+    rom: Vec<u8>,
+    beeper: Beeper,
+    samples: Vec<f32>,
+    audio: Vec<i16>,
+    video: Vec<u32>,
+}
+
+impl Game {
+    pub fn new(rom: &[u8], platform: Platform) -> Result<Self, RomError> {
+        let mut chip8 = Chip8::new(platform);
+        chip8.load_rom_bytes(rom)?;
+        let beeper = Beeper::default();
+
+        Ok(Self {
+            chip8,
+            rom: rom.to_vec(),
+            samples: vec![0.0; beeper.samples_per_frame()],
+            audio: Vec::new(),
+            video: Vec::new(),
+            beeper,
+        })
+    }
+
+    /// Restarts the game from a fresh machine with the same platform.
+    pub fn reset(&mut self) {
+        let mut chip8 = Chip8::new(self.chip8.platform.clone());
+        if chip8.load_rom_bytes(&self.rom).is_ok() {
+            self.chip8 = chip8;
+        }
+    }
+
+    /// Runs one frame with `keys` held, then renders its picture and sound.
+    pub fn run_frame(&mut self, keys: [bool; 16]) -> Result<(), ExecutionError> {
+        self.chip8.reset_keys();
+        for (i, _) in keys.iter().enumerate().filter(|(_, &down)| down) {
+            self.chip8.key_down(i);
+        }
+
+        let result = self.chip8.run_frame();
+
+        let (width, height) = self.size();
+        self.video.clear();
+        for y in 0..height {
+            for x in 0..width {
+                self.video
+                    .push(if self.chip8.has_color(x, y) { LIT } else { 0 });
+            }
+        }
+
+        self.beeper
+            .render(self.chip8.sound_active(), &mut self.samples);
+        self.audio.clear();
+        for sample in &self.samples {
+            let pcm = (sample * f32::from(i16::MAX)) as i16;
+            self.audio.extend_from_slice(&[pcm, pcm]);
+        }
+
+        result
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (
+            self.chip8.platform.video_width,
+            self.chip8.platform.video_height,
+        )
+    }
+
+    /// XRGB8888 pixels of the last frame, row by row.
+    pub fn video(&self) -> &[u32] {
+        &self.video
+    }
+
+    /// Interleaved stereo samples of the last frame.
+    pub fn audio(&self) -> &[i16] {
+        &self.audio
+    }
+}
+
+/// Core option keys and their `Description; value|value` definitions.
+pub fn options() -> &'static [(CString, CString)] {
+    static OPTIONS: OnceLock<Vec<(CString, CString)>> = OnceLock::new();
+
+    OPTIONS.get_or_init(|| {
+        let targets = Target::ALL.map(Target::name).join("|");
+        let mut options = vec![(
+            c"chipsters_target".to_owned(),
+            CString::new(format!("Target; {targets}")).unwrap(),
+        )];
+
+        for (name, _) in Quirks::all().iter_names() {
+            options.push((
+                CString::new(format!("chipsters_quirk_{}", name.to_lowercase())).unwrap(),
+                CString::new(format!("{name} quirk; default|on|off")).unwrap(),
+            ));
+        }
+
+        options
+    })
+}
+
+/// Builds the platform selected by the core options, read through `variable`.
+pub fn platform(variable: impl Fn(&CStr) -> Option<String>) -> Platform {
+    let target = variable(c"chipsters_target")
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(Target::CosmacVIP);
+    let mut platform = Platform::new(target);
+
+    for (name, quirk) in Quirks::all().iter_names() {
+        let key = CString::new(format!("chipsters_quirk_{}", name.to_lowercase())).unwrap();
+        match variable(&key).as_deref() {
+            Some("on") => platform.quirks.insert(quirk),
+            Some("off") => platform.quirks.remove(quirk),
+            _ => {}
+        }
+    }
+
+    platform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform() {
+        let platform = platform(|key| match key.to_str().unwrap() {
+            "chipsters_target" => Some("chip-48".to_string()),
+            "chipsters_quirk_wrap" => Some("on".to_string()),
+            "chipsters_quirk_jump" => Some("off".to_string()),
+            _ => Some("default".to_string()),
+        });
+
+        assert_eq!(Target::Chip48, platform.target);
+        assert_eq!(Quirks::SHIFT | Quirks::WRAP, platform.quirks);
+    }
+
+    #[test]
+    fn test_joypad() {
+        let mut buttons: Vec<_> = JOYPAD.iter().map(|(button, _)| *button).collect();
+        buttons.sort();
+        buttons.dedup();
+
+        assert_eq!(16, buttons.len());
+    }
+}
@@ -0,0 +1,386 @@
+//! A libretro core running ChipsteRS games in RetroArch and other libretro frontends.
+
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use chip8::audio::Beeper;
+use chip8::{State, SNAPSHOT_SIZE};
+use log::error;
+
+use crate::game::{Game, JOYPAD, KEYBOARD};
+use crate::sys::*;
+
+mod game;
+mod sys;
+
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_sample_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+static GAME: Mutex<Option<Game>> = Mutex::new(None);
+
+fn callbacks() -> MutexGuard<'static, Callbacks> {
+    CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn game() -> MutexGuard<'static, Option<Game>> {
+    GAME.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match callbacks().environment {
+        // SAFETY: the frontend gave us this callback and `data` matches what `cmd` expects.
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+fn variable(key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: std::ptr::null(),
+    };
+    if !environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        &mut variable as *mut _ as *mut c_void,
+    ) || variable.value.is_null()
+    {
+        return None;
+    }
+
+    // SAFETY: the frontend returned a NUL-terminated string that lives until the next call.
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    value.to_str().ok().map(str::to_string)
+}
+
+fn set_geometry(game: &Game) {
+    let (width, height) = game.size();
+    let mut geometry = geometry(width, height);
+    environment(
+        RETRO_ENVIRONMENT_SET_GEOMETRY,
+        &mut geometry as *mut _ as *mut c_void,
+    );
+}
+
+fn geometry(width: u16, height: u16) -> RetroGameGeometry {
+    RetroGameGeometry {
+        base_width: c_uint::from(width),
+        base_height: c_uint::from(height),
+        max_width: 128,
+        max_height: 64,
+        aspect_ratio: f32::from(width) / f32::from(height),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: RetroEnvironment) {
+    callbacks().environment = Some(callback);
+
+    let mut variables: Vec<RetroVariable> = game::options()
+        .iter()
+        .map(|(key, value)| RetroVariable {
+            key: key.as_ptr(),
+            value: value.as_ptr(),
+        })
+        .collect();
+    variables.push(RetroVariable {
+        key: std::ptr::null(),
+        value: std::ptr::null(),
+    });
+    environment(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: RetroVideoRefresh) {
+    callbacks().video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: RetroAudioSampleBatch) {
+    callbacks().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: RetroInputPoll) {
+    callbacks().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: RetroInputState) {
+    callbacks().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *game() = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"ChipsteRS".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8|c8|sc8|xo8|rom".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    let (width, height) = game().as_ref().map_or((64, 32), Game::size);
+
+    *info = RetroSystemAvInfo {
+        geometry: geometry(width, height),
+        timing: RetroSystemTiming {
+            fps: 60.0,
+            sample_rate: f64::from(Beeper::DEFAULT_SAMPLE_RATE),
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(game) = game().as_mut() {
+        game.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = *callbacks();
+    let mut guard = game();
+    let Some(game) = guard.as_mut() else {
+        return;
+    };
+
+    let mut updated = false;
+    environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        &mut updated as *mut _ as *mut c_void,
+    );
+    if updated {
+        let size = game.size();
+        game.chip8.platform = game::platform(variable);
+        if game.size() != size {
+            set_geometry(game);
+        }
+    }
+
+    let mut keys = [false; 16];
+    if let (Some(input_poll), Some(input_state)) = (callbacks.input_poll, callbacks.input_state) {
+        // SAFETY: both callbacks come from the frontend and take plain integers.
+        unsafe {
+            input_poll();
+            for (key, ((button, _), keycode)) in keys.iter_mut().zip(JOYPAD.iter().zip(KEYBOARD)) {
+                *key = input_state(0, RETRO_DEVICE_JOYPAD, 0, *button) != 0
+                    || input_state(0, RETRO_DEVICE_KEYBOARD, 0, c_uint::from(keycode)) != 0;
+            }
+        }
+    }
+
+    if let Err(err) = game.run_frame(keys) {
+        error!("Stopping: {err}");
+        game.chip8.state = State::Off;
+    }
+
+    let (width, height) = game.size();
+    if let Some(video_refresh) = callbacks.video_refresh {
+        // SAFETY: the buffer holds `width * height` pixels and outlives the call.
+        unsafe {
+            video_refresh(
+                game.video().as_ptr() as *const c_void,
+                c_uint::from(width),
+                c_uint::from(height),
+                usize::from(width) * 4,
+            );
+        }
+    }
+
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+        let audio = game.audio();
+        let mut written = 0;
+        while written < audio.len() {
+            // SAFETY: the slice is valid, and the count is in stereo frames.
+            let frames = unsafe {
+                audio_sample_batch(audio[written..].as_ptr(), (audio.len() - written) / 2)
+            };
+            if frames == 0 {
+                break;
+            }
+            written += frames * 2;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SNAPSHOT_SIZE
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let guard = game();
+    let Some(game) = guard.as_ref() else {
+        return false;
+    };
+    if size < SNAPSHOT_SIZE {
+        return false;
+    }
+
+    let snapshot = game.chip8.save_state();
+    std::ptr::copy_nonoverlapping(snapshot.as_ptr(), data as *mut u8, snapshot.len());
+
+    true
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut guard = game();
+    let Some(game) = guard.as_mut() else {
+        return false;
+    };
+
+    let snapshot = std::slice::from_raw_parts(data as *const u8, size);
+    game.chip8.load_state(snapshot).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+///
+/// `game` must be null or point to a `retro_game_info` whose `data` holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut format as *mut _ as *mut c_void,
+    ) {
+        return false;
+    }
+
+    let mut descriptors: Vec<RetroInputDescriptor> = JOYPAD
+        .iter()
+        .map(|(button, description)| RetroInputDescriptor {
+            port: 0,
+            device: RETRO_DEVICE_JOYPAD,
+            index: 0,
+            id: *button,
+            description: description.as_ptr(),
+        })
+        .collect();
+    descriptors.push(RetroInputDescriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: std::ptr::null(),
+    });
+    environment(
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+        descriptors.as_mut_ptr() as *mut c_void,
+    );
+
+    let rom = std::slice::from_raw_parts(game.data as *const u8, game.size);
+    match Game::new(rom, game::platform(variable)) {
+        Ok(loaded) => {
+            *self::game() = Some(loaded);
+            true
+        }
+        Err(err) => {
+            error!("Error loading game: {err}");
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *game() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match game().as_mut() {
+        Some(game) if id == RETRO_MEMORY_SYSTEM_RAM => {
+            game.chip8.ram_mut().as_mut_ptr() as *mut c_void
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match game().as_ref() {
+        Some(game) if id == RETRO_MEMORY_SYSTEM_RAM => game.chip8.ram().len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,274 @@
+//! Loads the built core the way a libretro frontend would and drives a few frames through it.
+
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use libloading::{Library, Symbol};
+
+const GET_VARIABLE: c_uint = 15;
+const SET_VARIABLES: c_uint = 16;
+const GET_VARIABLE_UPDATE: c_uint = 17;
+const SET_PIXEL_FORMAT: c_uint = 10;
+const SET_INPUT_DESCRIPTORS: c_uint = 11;
+const SET_GEOMETRY: c_uint = 37;
+
+#[repr(C)]
+struct Variable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+struct InputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+#[repr(C)]
+struct Geometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+/// What the frontend has seen from the core.
+#[derive(Default)]
+struct Frontend {
+    variables: Vec<String>,
+    descriptors: usize,
+    pixel_format: Option<c_uint>,
+    geometry: Option<(c_uint, c_uint)>,
+    target: Option<&'static CStr>,
+    updated: bool,
+    frame: Option<(c_uint, c_uint, Vec<u32>)>,
+    audio_frames: usize,
+}
+
+static FRONTEND: Mutex<Option<Frontend>> = Mutex::new(None);
+
+fn frontend<T>(f: impl FnOnce(&mut Frontend) -> T) -> T {
+    f(FRONTEND
+        .lock()
+        .unwrap()
+        .get_or_insert_with(Frontend::default))
+}
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    frontend(|frontend| match cmd {
+        SET_VARIABLES => {
+            let mut variable = data as *const Variable;
+            while !(*variable).key.is_null() {
+                let key = CStr::from_ptr((*variable).key);
+                frontend.variables.push(key.to_string_lossy().into_owned());
+                variable = variable.add(1);
+            }
+            true
+        }
+        SET_INPUT_DESCRIPTORS => {
+            let mut descriptor = data as *const InputDescriptor;
+            while !(*descriptor).description.is_null() {
+                frontend.descriptors += 1;
+                descriptor = descriptor.add(1);
+            }
+            true
+        }
+        SET_PIXEL_FORMAT => {
+            frontend.pixel_format = Some(*(data as *const c_uint));
+            true
+        }
+        GET_VARIABLE => {
+            let variable = &mut *(data as *mut Variable);
+            match CStr::from_ptr(variable.key).to_bytes() {
+                b"chipsters_target" if frontend.target.is_some() => {
+                    variable.value = frontend.target.unwrap().as_ptr();
+                    true
+                }
+                _ => false,
+            }
+        }
+        GET_VARIABLE_UPDATE => {
+            *(data as *mut bool) = std::mem::take(&mut frontend.updated);
+            true
+        }
+        SET_GEOMETRY => {
+            let geometry = &*(data as *const Geometry);
+            frontend.geometry = Some((geometry.base_width, geometry.base_height));
+            true
+        }
+        _ => false,
+    })
+}
+
+unsafe extern "C" fn video_refresh(
+    data: *const c_void,
+    width: c_uint,
+    height: c_uint,
+    pitch: usize,
+) {
+    assert_eq!(width as usize * 4, pitch);
+    let pixels = std::slice::from_raw_parts(data as *const u32, (width * height) as usize);
+    frontend(|frontend| frontend.frame = Some((width, height, pixels.to_vec())));
+}
+
+unsafe extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    frontend(|frontend| frontend.audio_frames += frames);
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+unsafe extern "C" fn input_state(
+    _port: c_uint,
+    _device: c_uint,
+    _index: c_uint,
+    _id: c_uint,
+) -> i16 {
+    0
+}
+
+fn core_path() -> PathBuf {
+    let name = format!(
+        "{}chipsters_libretro{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    );
+    let deps = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("the core is built before its integration tests")
+}
+
+#[test]
+fn test_core() {
+    let rom = include_bytes!("../../../roms/PONG");
+
+    unsafe {
+        let core = Library::new(core_path()).unwrap();
+        let call = |name: &[u8]| -> Symbol<unsafe extern "C" fn()> { core.get(name).unwrap() };
+
+        let api_version: Symbol<unsafe extern "C" fn() -> c_uint> =
+            core.get(b"retro_api_version").unwrap();
+        assert_eq!(1, api_version());
+
+        let set_environment: Symbol<unsafe extern "C" fn(_)> =
+            core.get(b"retro_set_environment").unwrap();
+        set_environment(environment as unsafe extern "C" fn(c_uint, *mut c_void) -> bool);
+        let set_video_refresh: Symbol<unsafe extern "C" fn(_)> =
+            core.get(b"retro_set_video_refresh").unwrap();
+        set_video_refresh(
+            video_refresh as unsafe extern "C" fn(*const c_void, c_uint, c_uint, usize),
+        );
+        let set_audio_sample_batch: Symbol<unsafe extern "C" fn(_)> =
+            core.get(b"retro_set_audio_sample_batch").unwrap();
+        set_audio_sample_batch(
+            audio_sample_batch as unsafe extern "C" fn(*const i16, usize) -> usize,
+        );
+        let set_input_poll: Symbol<unsafe extern "C" fn(_)> =
+            core.get(b"retro_set_input_poll").unwrap();
+        set_input_poll(input_poll as unsafe extern "C" fn());
+        let set_input_state: Symbol<unsafe extern "C" fn(_)> =
+            core.get(b"retro_set_input_state").unwrap();
+        set_input_state(input_state as unsafe extern "C" fn(c_uint, c_uint, c_uint, c_uint) -> i16);
+        call(b"retro_init")();
+
+        let mut info = std::mem::zeroed::<SystemInfo>();
+        let get_system_info: Symbol<unsafe extern "C" fn(*mut SystemInfo)> =
+            core.get(b"retro_get_system_info").unwrap();
+        get_system_info(&mut info);
+        assert_eq!(c"ChipsteRS", CStr::from_ptr(info.library_name));
+        assert!(!info.need_fullpath);
+
+        let load_game: Symbol<unsafe extern "C" fn(*const GameInfo) -> bool> =
+            core.get(b"retro_load_game").unwrap();
+        let game = GameInfo {
+            path: std::ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: std::ptr::null(),
+        };
+        assert!(load_game(&game));
+        frontend(|frontend| {
+            assert!(frontend.variables.contains(&"chipsters_target".to_string()));
+            assert!(frontend
+                .variables
+                .contains(&"chipsters_quirk_wrap".to_string()));
+            assert_eq!(16, frontend.descriptors);
+            assert_eq!(Some(1), frontend.pixel_format);
+        });
+
+        let run = call(b"retro_run");
+        for _ in 0..60 {
+            run();
+        }
+        frontend(|frontend| {
+            let (width, height, pixels) = frontend.frame.as_ref().unwrap();
+            assert_eq!((64, 32), (*width, *height));
+            assert!(pixels.iter().any(|&pixel| pixel != 0));
+            assert_eq!(60 * 735, frontend.audio_frames);
+        });
+
+        let serialize_size: Symbol<unsafe extern "C" fn() -> usize> =
+            core.get(b"retro_serialize_size").unwrap();
+        let serialize: Symbol<unsafe extern "C" fn(*mut c_void, usize) -> bool> =
+            core.get(b"retro_serialize").unwrap();
+        let unserialize: Symbol<unsafe extern "C" fn(*const c_void, usize) -> bool> =
+            core.get(b"retro_unserialize").unwrap();
+        let size = serialize_size();
+        let mut saved = vec![0u8; size];
+        assert!(serialize(saved.as_mut_ptr() as *mut c_void, size));
+        for _ in 0..10 {
+            run();
+        }
+        assert!(unserialize(saved.as_ptr() as *const c_void, size));
+        let mut restored = vec![0u8; size];
+        assert!(serialize(restored.as_mut_ptr() as *mut c_void, size));
+        assert_eq!(saved, restored);
+        assert!(!unserialize(saved.as_ptr() as *const c_void, size - 1));
+
+        let get_memory_size: Symbol<unsafe extern "C" fn(c_uint) -> usize> =
+            core.get(b"retro_get_memory_size").unwrap();
+        assert_eq!(4096, get_memory_size(2));
+
+        frontend(|frontend| {
+            frontend.target = Some(c"super-chip");
+            frontend.updated = true;
+        });
+        run();
+        frontend(|frontend| {
+            assert_eq!(Some((128, 64)), frontend.geometry);
+            assert_eq!(128 * 64, frontend.frame.as_ref().unwrap().2.len());
+        });
+
+        call(b"retro_unload_game")();
+        call(b"retro_deinit")();
+    }
+}
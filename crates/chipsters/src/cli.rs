@@ -0,0 +1,286 @@
+use anyhow::anyhow;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+use chip8::Target;
+use chipsters::config::{self, Settings, WindowConfig};
+use chipsters::keymap::Keymap;
+use chipsters::render::{Filter, Fit, RenderOptions, Rotation};
+use chipsters::Palette;
+
+/// A CHIP-8 emulator that plays in a window or a terminal.
+#[derive(Debug, Parser)]
+#[command(
+    name = "chipsters",
+    version,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub tool: Option<Tool>,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    /// Plays in the terminal instead of a window.
+    #[arg(long)]
+    pub terminal: bool,
+
+    /// Draws the terminal display with braille characters instead of half blocks.
+    #[arg(long, requires = "terminal")]
+    pub braille: bool,
+
+    /// Window size as a multiple of the display resolution.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub scale: Option<u32>,
+
+    /// Starts the window fullscreen.
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// How the display fits the window: integer (whole multiples with borders), aspect or
+    /// stretch. Defaults to aspect.
+    #[arg(long)]
+    pub fit: Option<Fit>,
+
+    /// Pixel-art upscaler: none, scale2x, scale3x or xbr-lite.
+    #[arg(long)]
+    pub filter: Option<Filter>,
+
+    /// Darkens every second line like a CRT.
+    #[arg(long)]
+    pub scanlines: bool,
+
+    /// Display colours: classic, amber, green, octo, FOREGROUND,BACKGROUND as RRGGBB hex, or 4 or
+    /// 16 RRGGBB colours by pixel value, background first.
+    #[arg(long)]
+    pub palette: Option<Palette>,
+
+    /// Fades pixels out instead of turning them off at once, keeping this much of their
+    /// brightness each frame, from 0 (off) to 0.95.
+    #[arg(long, value_name = "DECAY")]
+    pub phosphor: Option<f32>,
+
+    /// Turns the display clockwise by 0, 90, 180 or 270 degrees, for ROMs played on their side.
+    #[arg(long, value_name = "DEGREES")]
+    pub rotation: Option<Rotation>,
+
+    /// Mirrors the display left to right.
+    #[arg(long)]
+    pub flip_horizontal: bool,
+
+    /// Mirrors the display top to bottom.
+    #[arg(long)]
+    pub flip_vertical: bool,
+
+    /// Beep volume from 0 to 1.
+    #[arg(long)]
+    pub volume: Option<f32>,
+
+    /// Keyboard layout for the keypad: qwerty, azerty, dvorak or dream-6800.
+    #[arg(long, value_parser = parse_layout)]
+    pub layout: Option<String>,
+
+    /// Starts the ROM paused.
+    #[arg(long)]
+    pub paused: bool,
+
+    /// A ROM file, or a folder or .zip ROM pack to pick one from.
+    #[arg(required = true)]
+    pub rom_path: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The settings given on the command line, which override the config file.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            palette: self.palette,
+            phosphor: self.phosphor,
+            rotation: self.rotation,
+            flip_horizontal: self.flip_horizontal.then_some(true),
+            flip_vertical: self.flip_vertical.then_some(true),
+            volume: self.volume,
+            layout: self.layout.clone(),
+            ..self.machine.settings()
+        }
+    }
+
+    /// How the window draws the display, from these options and then the config's.
+    pub fn render_options(&self, window: &WindowConfig) -> RenderOptions {
+        RenderOptions {
+            filter: self.filter.or(window.filter).unwrap_or_default(),
+            scanlines: self.scanlines || window.scanlines,
+            fit: self.fit.or(window.fit).unwrap_or_default(),
+        }
+    }
+}
+
+/// Utilities that run without a window.
+#[derive(Debug, Subcommand)]
+pub enum Tool {
+    /// Runs a ROM and records its beeper to a WAV file.
+    Wav {
+        rom_path: PathBuf,
+        wav_path: PathBuf,
+        /// 60 Hz frames to record.
+        #[arg(long, default_value_t = 600)]
+        frames: usize,
+    },
+    /// Runs a ROM and saves its display to a PNG file, drawn with the ROM's palette.
+    Screenshot {
+        rom_path: PathBuf,
+        png_path: PathBuf,
+        /// 60 Hz frames to run first.
+        #[arg(long, default_value_t = 60)]
+        frames: usize,
+        /// Display colours, as for playing.
+        #[arg(long)]
+        palette: Option<Palette>,
+        /// Size as a multiple of the filtered display.
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=64))]
+        scale: u32,
+        /// Pixel-art upscaler: none, scale2x, scale3x or xbr-lite.
+        #[arg(long)]
+        filter: Option<Filter>,
+        /// Darkens every second line like a CRT.
+        #[arg(long)]
+        scanlines: bool,
+    },
+    /// Prints the disassembly of the code reachable in a ROM.
+    Disasm { rom_path: PathBuf },
+    /// Prints a ROM as structured pseudo-code.
+    Decompile { rom_path: PathBuf },
+    /// Reports likely bugs in a ROM and the quirks it depends on.
+    Lint {
+        rom_path: PathBuf,
+        /// Instructions to run while looking for problems.
+        #[arg(long, default_value_t = 100_000)]
+        cycles: usize,
+    },
+}
+
+/// Options for the emulated machine, shared by playing and the utilities.
+#[derive(Debug, Args)]
+pub struct MachineArgs {
+    /// Config file to read instead of chipsters/config.toml in the user's config directory.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Machine to emulate, which picks the display size, speed and quirks. Defaults to
+    /// cosmac-vip.
+    #[arg(long, global = true)]
+    pub target: Option<Target>,
+
+    /// Turns a quirk of the target on, or off with =off: vf-reset, load-store-inc-i, vblank,
+    /// wrap, shift or jump. Can be repeated.
+    #[arg(long = "quirk", value_name = "QUIRK[=on|off]", global = true, value_parser = parse_quirk)]
+    pub quirks: Vec<(String, bool)>,
+
+    /// Instructions per second, run as a whole number per 60 Hz frame.
+    #[arg(long, global = true, value_parser = clap::value_parser!(u32).range(1..))]
+    pub ips: Option<u32>,
+
+    /// Seeds the random number generator, for reproducible runs.
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+}
+
+impl MachineArgs {
+    pub fn settings(&self) -> Settings {
+        Settings {
+            target: self.target,
+            quirks: self.quirks.iter().cloned().collect(),
+            ips: self.ips,
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_layout(layout: &str) -> anyhow::Result<String> {
+    Keymap::layout(layout)
+        .map(|_| layout.to_string())
+        .ok_or_else(|| anyhow!("Expected one of {}", Keymap::LAYOUTS.join(", ")))
+}
+
+fn parse_quirk(arg: &str) -> anyhow::Result<(String, bool)> {
+    let (name, enabled) = match arg.split_once('=') {
+        Some((name, "on")) => (name, true),
+        Some((name, "off")) => (name, false),
+        Some((_, value)) => return Err(anyhow!("Expected on or off, found {value:?}")),
+        None => (arg, true),
+    };
+    config::quirk(name).ok_or_else(|| anyhow!("Unknown quirk {name:?}"))?;
+
+    Ok((name.to_string(), enabled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip8::Quirks;
+
+    #[test]
+    fn test_machine_args() {
+        let cli = Cli::try_parse_from([
+            "chipsters",
+            "--target",
+            "super-chip",
+            "--quirk",
+            "wrap",
+            "--quirk",
+            "load-store-inc-i=off",
+            "--ips",
+            "600",
+            "--rotation",
+            "270",
+            "--flip-vertical",
+            "PONG",
+        ])
+        .unwrap();
+        let platform = cli.machine.settings().platform().unwrap();
+
+        assert_eq!(Target::SuperChip, platform.target);
+        assert_eq!(Quirks::WRAP, platform.quirks);
+        assert_eq!(10, platform.tick_rate);
+        assert!(cli.tool.is_none());
+
+        let orientation = cli.settings().orientation();
+        assert_eq!(Rotation::R270, orientation.rotation);
+        assert!(!orientation.flip_horizontal && orientation.flip_vertical);
+    }
+
+    #[test]
+    fn test_tools() {
+        let cli =
+            Cli::try_parse_from(["chipsters", "wav", "PONG", "pong.wav", "--seed", "7"]).unwrap();
+        assert!(matches!(cli.tool, Some(Tool::Wav { frames: 600, .. })));
+        assert_eq!(Some(7), cli.machine.seed);
+
+        let cli = Cli::try_parse_from([
+            "chipsters",
+            "screenshot",
+            "PONG",
+            "pong.png",
+            "--filter",
+            "scale3x",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.tool,
+            Some(Tool::Screenshot {
+                frames: 60,
+                scale: 1,
+                filter: Some(Filter::Scale3x),
+                scanlines: false,
+                ..
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["chipsters"]).is_err());
+        assert!(Cli::try_parse_from(["chipsters", "--quirk", "warp", "PONG"]).is_err());
+        assert!(Cli::try_parse_from(["chipsters", "--target", "chip-9", "PONG"]).is_err());
+        assert!(Cli::try_parse_from(["chipsters", "--layout", "colemak", "PONG"]).is_err());
+        assert!(Cli::try_parse_from(["chipsters", "--rotation", "45", "PONG"]).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+//! The user's `config.toml`, with settings for every ROM and overrides for single ROMs.
+//!
+//! ```toml
+//! target = "super-chip"
+//! ips = 900
+//! palette = "amber"
+//! phosphor = 0.6
+//! volume = 0.5
+//! turbo = "4x"
+//! layout = "azerty"
+//!
+//! [quirks]
+//! wrap = true
+//!
+//! [keys]
+//! 5 = "wi"
+//!
+//! [window]
+//! scale = 12
+//! fit = "integer"
+//! filter = "scale2x"
+//! scanlines = true
+//!
+//! # Keyed by file name or by the SHA-1 of the ROM, which wins.
+//! [roms.PONG]
+//! target = "cosmac-vip"
+//!
+//! [roms.VBRIX]
+//! rotation = 90
+//! ```
+//!
+//! Settings changed from inside chipsters, such as the palette, are kept per ROM in `saved.toml`
+//! next to it, which the config file overrides.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha1_smol::Sha1;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chip8::{Platform, Quirks, Target};
+
+use crate::frontend::Speed;
+use crate::keymap::Keymap;
+use crate::render::{Filter, Fit, Orientation, Rotation};
+use crate::Palette;
+
+/// Settings that can be given for every ROM and overridden for single ROMs. Unset fields fall
+/// back to the target's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(
+        deserialize_with = "parse",
+        serialize_with = "display",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target: Option<Target>,
+    /// Quirks turned on or off, by name such as `vf-reset`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub quirks: BTreeMap<String, bool>,
+    /// Instructions per second.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<u32>,
+    /// The speed turbo switches to.
+    #[serde(
+        deserialize_with = "parse",
+        serialize_with = "display",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub turbo: Option<Speed>,
+    #[serde(
+        deserialize_with = "parse",
+        serialize_with = "display",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub palette: Option<Palette>,
+    /// How much of its brightness an unlit pixel keeps each frame, 0 to turn phosphor persistence
+    /// off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phosphor: Option<f32>,
+    /// Degrees the display is turned clockwise: 0, 90, 180 or 270.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Rotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flip_horizontal: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flip_vertical: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+    /// Preset keyboard layout, one of `Keymap::LAYOUTS`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Host keys replacing the layout's for a CHIP-8 key, one per character, such as `A = "z/"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// Replaces the settings that `other` sets.
+    pub fn merge(&mut self, other: &Settings) {
+        self.target = other.target.or(self.target);
+        self.quirks.extend(other.quirks.clone());
+        self.ips = other.ips.or(self.ips);
+        self.turbo = other.turbo.or(self.turbo);
+        self.palette = other.palette.or(self.palette);
+        self.phosphor = other.phosphor.or(self.phosphor);
+        self.rotation = other.rotation.or(self.rotation);
+        self.flip_horizontal = other.flip_horizontal.or(self.flip_horizontal);
+        self.flip_vertical = other.flip_vertical.or(self.flip_vertical);
+        self.volume = other.volume.or(self.volume);
+        self.layout = other.layout.clone().or(self.layout.take());
+        self.keys.extend(other.keys.clone());
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        Orientation {
+            rotation: self.rotation.unwrap_or_default(),
+            flip_horizontal: self.flip_horizontal.unwrap_or(false),
+            flip_vertical: self.flip_vertical.unwrap_or(false),
+        }
+    }
+
+    pub fn platform(&self) -> Result<Platform> {
+        let mut platform = Platform::new(self.target.unwrap_or(Target::CosmacVIP));
+        for (name, &enabled) in &self.quirks {
+            let quirk = quirk(name).ok_or_else(|| anyhow!("Unknown quirk {name:?}"))?;
+            platform.quirks.set(quirk, enabled);
+        }
+        if let Some(ips) = self.ips {
+            platform.tick_rate = (ips.saturating_add(30) / 60).clamp(1, u32::from(u16::MAX)) as u16;
+        }
+
+        Ok(platform)
+    }
+
+    /// The layout, QWERTY by default, with the keys set for single CHIP-8 keys.
+    pub fn keymap(&self) -> Result<Keymap> {
+        let mut keymap = match &self.layout {
+            Some(layout) => Keymap::layout(layout).ok_or_else(|| {
+                anyhow!(
+                    "Unknown layout {layout:?}, expected one of {}",
+                    Keymap::LAYOUTS.join(", ")
+                )
+            })?,
+            None => Keymap::default(),
+        };
+        for (key, hosts) in &self.keys {
+            let index = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&index| index < 16)
+                .ok_or_else(|| anyhow!("Invalid CHIP-8 key {key:?}, expected 0 to F"))?;
+            keymap.clear(usize::from(index));
+            for host in hosts.chars() {
+                keymap.bind(usize::from(index), host);
+            }
+        }
+
+        Ok(keymap)
+    }
+}
+
+/// Window size and drawing, which can only be set for every ROM.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// Window size as a multiple of the display resolution, used instead of `width` and `height`.
+    pub scale: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: bool,
+    #[serde(deserialize_with = "parse")]
+    pub fit: Option<Fit>,
+    #[serde(deserialize_with = "parse")]
+    pub filter: Option<Filter>,
+    pub scanlines: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub settings: Settings,
+    pub window: WindowConfig,
+    /// Overrides keyed by ROM file name or SHA-1.
+    pub roms: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    /// `chipsters/config.toml` in the user's config directory, such as `~/.config` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chipsters").join("config.toml"))
+    }
+
+    /// Reads the config at `path`, or at `default_path` when not given, where a missing file means
+    /// the defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let text = fs::read_to_string(&path)
+            .map_err(|err| anyhow!("Error reading config {}: {}", path.display(), err))?;
+        text.parse()
+    }
+
+    /// The settings for a ROM, with the override for its file name and then for its hash applied.
+    pub fn rom_settings(&self, name: &str, rom: &[u8]) -> Settings {
+        self.saved_rom_settings(&SavedSettings::default(), name, rom)
+    }
+
+    /// Like `rom_settings`, with the settings saved for the ROM applied before its overrides.
+    pub fn saved_rom_settings(&self, saved: &SavedSettings, name: &str, rom: &[u8]) -> Settings {
+        let mut settings = self.settings.clone();
+        let file_name = file_name(name);
+        if let Some(saved) = saved.roms.get(file_name) {
+            settings.merge(saved);
+        }
+        for key in [file_name.to_string(), rom_hash(rom)] {
+            if let Some(rom_settings) = self.roms.get(&key) {
+                settings.merge(rom_settings);
+            }
+        }
+
+        settings
+    }
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text).map_err(|err| anyhow!("Invalid config: {err}"))?;
+        for settings in std::iter::once(&config.settings).chain(config.roms.values()) {
+            settings.platform()?;
+            settings.keymap()?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Settings changed from inside chipsters, saved per ROM file name.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SavedSettings {
+    roms: BTreeMap<String, Settings>,
+    /// Where changes are written, if anywhere.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl SavedSettings {
+    /// `chipsters/saved.toml` in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chipsters").join("saved.toml"))
+    }
+
+    /// Reads the settings saved at `path`, which need not exist, and saves changes back there.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut saved = if path.exists() {
+            let text = fs::read_to_string(path)
+                .map_err(|err| anyhow!("Error reading {}: {}", path.display(), err))?;
+            toml::from_str(&text)
+                .map_err(|err| anyhow!("Invalid saved settings {}: {}", path.display(), err))?
+        } else {
+            Self::default()
+        };
+        saved.path = Some(path.to_path_buf());
+
+        Ok(saved)
+    }
+
+    pub fn rom(&self, name: &str) -> Option<&Settings> {
+        self.roms.get(file_name(name))
+    }
+
+    /// Changes the settings saved for the ROM with file name `name`, and writes them out.
+    pub fn update(&mut self, name: &str, change: impl FnOnce(&mut Settings)) -> Result<()> {
+        change(self.roms.entry(file_name(name).to_string()).or_default());
+
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, toml::to_string(self)?)
+                .map_err(|err| anyhow!("Error saving {}: {}", path.display(), err))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The last component of a ROM name, which may be a path inside a directory or pack.
+fn file_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// The lowercase hex SHA-1 of a ROM, as used by ROM databases.
+pub fn rom_hash(rom: &[u8]) -> String {
+    Sha1::from(rom).digest().to_string()
+}
+
+/// Looks up a quirk by name, such as `vf-reset` or `VF_RESET`.
+pub fn quirk(name: &str) -> Option<Quirks> {
+    Quirks::from_name(&name.to_ascii_uppercase().replace('-', "_"))
+}
+
+fn display<S, T>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Display,
+{
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn parse<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        target = "super-chip"
+        ips = 600
+        palette = "amber"
+        phosphor = 0.5
+        turbo = "4x"
+
+        layout = "dvorak"
+
+        [quirks]
+        wrap = true
+
+        [keys]
+        5 = "Wi"
+        f = "b"
+
+        [window]
+        width = 640
+        height = 320
+        filter = "xbr-lite"
+
+        [roms.PONG]
+        target = "cosmac-vip"
+
+        [roms.VBRIX]
+        rotation = 270
+        flip_vertical = true
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let config: Config = CONFIG.parse().unwrap();
+
+        let platform = config.settings.platform().unwrap();
+        assert_eq!(Target::SuperChip, platform.target);
+        assert_eq!(Quirks::LOAD_STORE_INC_I | Quirks::WRAP, platform.quirks);
+        assert_eq!(10, platform.tick_rate);
+        assert_eq!(Some(Speed::Turbo(4)), config.settings.turbo);
+        assert_eq!(Some(Palette::AMBER), config.settings.palette);
+        assert_eq!(Some(0.5), config.settings.phosphor);
+        assert_eq!(Some(640), config.window.width);
+        assert_eq!(Some(Filter::XbrLite), config.window.filter);
+
+        let keymap = config.settings.keymap().unwrap();
+        assert_eq!(&['w', 'i'], keymap.hosts(5));
+        assert_eq!(&['b'], keymap.hosts(0xF));
+        assert_eq!(&['q'], keymap.hosts(0));
+
+        assert!("target = \"chip-9\"".parse::<Config>().is_err());
+        assert!("[quirks]\nwarp = true".parse::<Config>().is_err());
+        assert!("[keys]\n10 = \"x\"".parse::<Config>().is_err());
+        assert!("layout = \"colemak\"".parse::<Config>().is_err());
+        assert!("[window]\nsize = 3".parse::<Config>().is_err());
+        assert!("rotation = 45".parse::<Config>().is_err());
+
+        let fastest: Config = format!("ips = {}", u32::MAX).parse().unwrap();
+        assert_eq!(u16::MAX, fastest.settings.platform().unwrap().tick_rate);
+    }
+
+    #[test]
+    fn test_rom_settings() {
+        let mut config: Config = CONFIG.parse().unwrap();
+
+        let pong = config.rom_settings("Pack.zip/games/PONG", &[0x12, 0x00]);
+        assert_eq!(Some(Target::CosmacVIP), pong.target);
+        assert_eq!(Some(600), pong.ips);
+        assert_eq!(Some(Palette::AMBER), pong.palette);
+
+        let hash = rom_hash(&[0x12, 0x00]);
+        let ips = Settings {
+            ips: Some(900),
+            ..Default::default()
+        };
+        config.roms.insert(hash, ips);
+        assert_eq!(Some(900), config.rom_settings("PONG", &[0x12, 0x00]).ips);
+        assert_eq!(Some(600), config.rom_settings("BRIX", &[0x12, 0x02]).ips);
+
+        let vbrix = config.rom_settings("VBRIX", &[0x12, 0x04]).orientation();
+        assert_eq!(Rotation::R270, vbrix.rotation);
+        assert!(!vbrix.flip_horizontal && vbrix.flip_vertical);
+        assert_eq!(Orientation::default(), pong.orientation());
+    }
+
+    #[test]
+    fn test_saved_settings() {
+        let config: Config = CONFIG.parse().unwrap();
+        let path =
+            std::env::temp_dir().join(format!("chipsters-saved-{}.toml", std::process::id()));
+        let mut saved = SavedSettings::load(&path).unwrap();
+        saved
+            .update("Pack.zip/PONG", |settings| {
+                settings.palette = Some(Palette::OCTO);
+                settings.target = Some(Target::SuperChip);
+                settings.rotation = Some(Rotation::R90);
+            })
+            .unwrap();
+
+        let saved = SavedSettings::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Some(Palette::OCTO), saved.rom("PONG").unwrap().palette);
+        assert_eq!(Some(Rotation::R90), saved.rom("PONG").unwrap().rotation);
+
+        // The config's override for the ROM wins over what was saved.
+        let pong = config.saved_rom_settings(&saved, "PONG", &[0x12, 0x00]);
+        assert_eq!(Some(Palette::OCTO), pong.palette);
+        assert_eq!(Some(Target::CosmacVIP), pong.target);
+    }
+}
@@ -0,0 +1,41 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Clock;
+
+/// Sleeps out the remainder of each 60 Hz frame.
+#[derive(Debug)]
+pub struct SleepClock {
+    frame_start: Instant,
+}
+
+impl SleepClock {
+    const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            frame_start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SleepClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SleepClock {
+    fn wait_frame(&mut self) {
+        let elapsed = self.frame_start.elapsed();
+        if elapsed < Self::FRAME_DURATION {
+            thread::sleep(Self::FRAME_DURATION - elapsed);
+        }
+        self.frame_start = Instant::now();
+    }
+
+    fn frame_over(&self) -> bool {
+        self.frame_start.elapsed() >= Self::FRAME_DURATION
+    }
+}
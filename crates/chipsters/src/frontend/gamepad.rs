@@ -0,0 +1,101 @@
+use gilrs::{EventType, Gilrs};
+
+use super::{Command, Input, InputSource};
+use crate::keymap::Keymap;
+use crate::padmap::{Button, PadMap};
+
+/// Adds the buttons of every connected gamepad to another input source. Start pauses and loads
+/// from the menu, select goes back and the D-pad also moves through menus.
+#[derive(Debug)]
+pub struct GamepadInput<I> {
+    inner: I,
+    /// Missing when the platform has no gamepad support, leaving only `inner`.
+    gilrs: Option<Gilrs>,
+    padmap: PadMap,
+}
+
+impl<I: InputSource> GamepadInput<I> {
+    /// The gilrs name of each button that can be bound.
+    const BUTTONS: [(gilrs::Button, Button); 12] = [
+        (gilrs::Button::DPadUp, Button::Up),
+        (gilrs::Button::DPadDown, Button::Down),
+        (gilrs::Button::DPadLeft, Button::Left),
+        (gilrs::Button::DPadRight, Button::Right),
+        (gilrs::Button::South, Button::South),
+        (gilrs::Button::East, Button::East),
+        (gilrs::Button::West, Button::West),
+        (gilrs::Button::North, Button::North),
+        (gilrs::Button::LeftTrigger, Button::LeftBumper),
+        (gilrs::Button::RightTrigger, Button::RightBumper),
+        (gilrs::Button::LeftTrigger2, Button::LeftTrigger),
+        (gilrs::Button::RightTrigger2, Button::RightTrigger),
+    ];
+
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            gilrs: Gilrs::new().ok(),
+            padmap: PadMap::default(),
+        }
+    }
+
+    fn button(button: gilrs::Button) -> Option<Button> {
+        Self::BUTTONS
+            .iter()
+            .find(|(host, _)| *host == button)
+            .map(|&(_, button)| button)
+    }
+
+    fn commands(button: gilrs::Button) -> &'static [Command] {
+        match button {
+            // Pausing comes first so that loading from the menu does not pause the new ROM.
+            gilrs::Button::Start => &[Command::TogglePause, Command::Select],
+            gilrs::Button::Select => &[Command::Back],
+            gilrs::Button::DPadUp => &[Command::Up],
+            gilrs::Button::DPadDown => &[Command::Down],
+            gilrs::Button::DPadLeft => &[Command::Left],
+            gilrs::Button::DPadRight => &[Command::Right],
+            _ => &[],
+        }
+    }
+}
+
+impl<I: InputSource> InputSource for GamepadInput<I> {
+    fn poll(&mut self) -> Input {
+        let mut input = self.inner.poll();
+        let Some(gilrs) = &mut self.gilrs else {
+            return input;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event.event {
+                input.commands.extend(Self::commands(button));
+                input.buttons.extend(Self::button(button));
+            }
+        }
+
+        let held: Vec<Button> = gilrs
+            .gamepads()
+            .flat_map(|(_, gamepad)| {
+                Self::BUTTONS
+                    .into_iter()
+                    .filter(move |&(host, _)| gamepad.is_pressed(host))
+                    .map(|(_, button)| button)
+            })
+            .collect();
+        let keys = self.padmap.pressed(held);
+        for (down, pad_down) in input.keys.iter_mut().zip(keys) {
+            *down |= pad_down;
+        }
+
+        input
+    }
+
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.inner.set_keymap(keymap);
+    }
+
+    fn set_padmap(&mut self, padmap: PadMap) {
+        self.padmap = padmap;
+    }
+}
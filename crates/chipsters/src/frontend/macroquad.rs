@@ -0,0 +1,468 @@
+use anyhow::{anyhow, Result};
+use macroquad::audio::{
+    load_sound_from_bytes, play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound,
+};
+use macroquad::miniquad::window::{set_fullscreen, set_window_size};
+use macroquad::prelude::*;
+
+use chip8::audio::{write_wav, Beeper};
+use chip8::{Chip8, Platform};
+
+#[cfg(feature = "gamepad")]
+use super::GamepadInput;
+use super::{AudioSink, Command, Input, InputSource, SleepClock, Speed, VideoSink, View};
+use crate::keymap::Keymap;
+use crate::phosphor::Phosphor;
+use crate::render::{Frame, Orientation, RenderOptions};
+use crate::{Palette, Runner};
+
+/// The windowed emulator, with every part of the frontend backed by macroquad.
+pub type ChipsteRS = Runner<MacroquadVideo, WindowInput, MacroquadAudio, SleepClock>;
+
+/// Keyboard input, with gamepads added by the `gamepad` feature.
+#[cfg(feature = "gamepad")]
+pub type WindowInput = GamepadInput<MacroquadInput>;
+#[cfg(not(feature = "gamepad"))]
+pub type WindowInput = MacroquadInput;
+
+impl ChipsteRS {
+    /// Creates the window-backed runner. Audio output needs the `audio` feature; without it the
+    /// beep is silent. Gamepads need the `gamepad` feature.
+    pub async fn window(options: WindowOptions) -> Result<Self> {
+        let input = MacroquadInput::default();
+        #[cfg(feature = "gamepad")]
+        let input = GamepadInput::new(input);
+
+        Ok(Runner::new(
+            MacroquadVideo::new(options),
+            input,
+            MacroquadAudio::new(Beeper::default()).await?,
+            SleepClock::new(),
+        ))
+    }
+}
+
+/// How the window is sized, coloured and drawn.
+#[derive(Debug, Clone)]
+pub struct WindowOptions {
+    /// Display resolution of the emulated machine.
+    pub resolution: (u16, u16),
+    /// Window size as a multiple of `resolution`, used instead of `size`.
+    pub scale: Option<u32>,
+    pub size: (u32, u32),
+    pub fullscreen: bool,
+    pub palette: Palette,
+    pub render: RenderOptions,
+    /// How the display starts out turned, which swaps the window's width and height for a
+    /// quarter turn.
+    pub orientation: Orientation,
+}
+
+impl WindowOptions {
+    pub const DEFAULT_SIZE: (u32, u32) = (1200, 600);
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        let platform = Platform::default();
+        Self {
+            resolution: (platform.video_width, platform.video_height),
+            scale: None,
+            size: Self::DEFAULT_SIZE,
+            fullscreen: false,
+            palette: Palette::default(),
+            render: RenderOptions::default(),
+            orientation: Orientation::default(),
+        }
+    }
+}
+
+fn color([r, g, b]: [u8; 3]) -> Color {
+    Color::from_rgba(r, g, b, 255)
+}
+
+#[derive(Debug)]
+pub struct MacroquadVideo {
+    buffer: Image,
+    texture: Texture2D,
+    speed: Speed,
+    palette: Palette,
+    background: Color,
+    phosphor: Phosphor,
+    render: RenderOptions,
+    orientation: Orientation,
+    fullscreen: bool,
+}
+
+impl MacroquadVideo {
+    #[must_use]
+    pub fn new(options: WindowOptions) -> Self {
+        let (video_width, video_height) = options.resolution;
+        let (scaled_width, scaled_height) = options.orientation.size(video_width, video_height);
+        let size = options.orientation.size(options.size.0, options.size.1);
+        let (width, height) = options.scale.map_or(size, |scale| {
+            (
+                u32::from(scaled_width) * scale,
+                u32::from(scaled_height) * scale,
+            )
+        });
+        request_new_screen_size(width as f32, height as f32);
+        set_window_size(width, height);
+        if options.fullscreen {
+            set_fullscreen(true);
+        }
+
+        let background = color(options.palette.background());
+        let buffer = Image::gen_image_color(video_width, video_height, background);
+        let texture = Texture2D::from_image(&buffer);
+        texture.set_filter(FilterMode::Nearest);
+
+        Self {
+            buffer,
+            texture,
+            speed: Speed::Normal,
+            palette: options.palette,
+            background,
+            phosphor: Phosphor::default(),
+            render: options.render,
+            orientation: options.orientation,
+            fullscreen: options.fullscreen,
+        }
+    }
+
+    fn draw_game(&mut self, chip8: &Chip8) {
+        let (width, height) = (chip8.platform.video_width, chip8.platform.video_height);
+        // Only emulated frames fade the phosphor, so a paused picture keeps its trails.
+        if self.phosphor.len() != chip8.framebuffer().len() {
+            self.phosphor.update(chip8.framebuffer());
+        }
+        let frame = Frame::new(
+            usize::from(width),
+            usize::from(height),
+            self.phosphor.colors(&self.palette),
+        );
+        // Turned first, so that scanlines stay horizontal.
+        let frame = self.render.render(&frame.orient(self.orientation));
+
+        // Resolution, orientation and filter changes change the size of the frame.
+        if (
+            usize::from(self.buffer.width),
+            usize::from(self.buffer.height),
+        ) != (frame.width, frame.height)
+        {
+            self.buffer =
+                Image::gen_image_color(frame.width as u16, frame.height as u16, self.background);
+            self.texture = Texture2D::from_image(&self.buffer);
+            self.texture.set_filter(FilterMode::Nearest);
+        }
+        self.buffer.bytes.copy_from_slice(frame.as_bytes());
+        self.texture.update(&self.buffer);
+
+        let (width, height) = self.orientation.size(width, height);
+        let [x, y, dest_width, dest_height] = self.render.fit.viewport(
+            (f32::from(width), f32::from(height)),
+            (screen_width(), screen_height()),
+        );
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(dest_width, dest_height)),
+                ..Default::default()
+            },
+        );
+
+        if self.speed != Speed::Normal {
+            draw_text(&self.speed.to_string(), 10.0, 40.0, 40.0, YELLOW);
+        }
+    }
+
+    fn draw_menu(titles: &[String], cursor: usize) {
+        let mut color: Color;
+        for (i, rom_title) in titles[cursor..].iter().enumerate() {
+            color = if i == 0 { BLUE } else { WHITE };
+            draw_text(
+                format!("{}/{} {}", cursor + i + 1, titles.len(), rom_title).as_str(),
+                screen_width() / 2.0 - 100.0,
+                30. + ((i + 1) as f32 * 30.0),
+                40.0,
+                color,
+            );
+        }
+    }
+
+    fn draw_binding(key: usize, device: &str, bound: &[String]) {
+        let lines = [
+            format!("Press {device} for {key:X}"),
+            format!("Bound: {}", bound.join(" ")),
+            "Enter next key  Esc done".to_string(),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                screen_width() / 2.0 - 200.0,
+                screen_height() / 2.0 - 60.0 + i as f32 * 50.0,
+                40.0,
+                WHITE,
+            );
+        }
+    }
+}
+
+impl Default for MacroquadVideo {
+    fn default() -> Self {
+        Self::new(WindowOptions::default())
+    }
+}
+
+impl VideoSink for MacroquadVideo {
+    fn present(&mut self, view: View<'_>) {
+        clear_background(self.background);
+
+        match view {
+            View::Game(chip8) => self.draw_game(chip8),
+            // The game stays visible behind the label so frames can be stepped through.
+            View::Paused(chip8) => {
+                self.draw_game(chip8);
+                draw_text_ex(
+                    "Paused",
+                    screen_width() / 2.0 - 100.0,
+                    screen_height() / 2.0 - 100.0,
+                    TextParams {
+                        font_size: 100,
+                        font_scale: 1.0,
+                        color: WHITE,
+                        ..Default::default()
+                    },
+                );
+            }
+            View::Menu { titles, cursor } => Self::draw_menu(titles, cursor),
+            View::Binding { key, keymap } => {
+                let hosts: Vec<String> = keymap.hosts(key).iter().map(char::to_string).collect();
+                Self::draw_binding(key, "keys", &hosts);
+            }
+            View::PadBinding { key, padmap } => {
+                let buttons: Vec<String> = padmap
+                    .buttons(key)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                Self::draw_binding(key, "buttons", &buttons);
+            }
+        }
+    }
+
+    fn frames_ran(&mut self, chip8: &Chip8) {
+        self.phosphor.update(chip8.framebuffer());
+    }
+
+    fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.background = color(palette.background());
+    }
+
+    fn set_phosphor(&mut self, decay: f32) {
+        self.phosphor.set_decay(decay);
+    }
+
+    /// Swaps the window's width and height when the display turns on or off its side.
+    fn set_orientation(&mut self, orientation: Orientation) {
+        if orientation.rotation.is_sideways() != self.orientation.rotation.is_sideways()
+            && !self.fullscreen
+        {
+            let (width, height) = (screen_height(), screen_width());
+            request_new_screen_size(width, height);
+            set_window_size(width as u32, height as u32);
+        }
+        self.orientation = orientation;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MacroquadInput {
+    keymap: Keymap,
+}
+
+impl MacroquadInput {
+    const COMMAND_KEYS: [(KeyCode, Command); 18] = [
+        (KeyCode::Escape, Command::Back),
+        (KeyCode::Space, Command::TogglePause),
+        (KeyCode::Minus, Command::Slower),
+        (KeyCode::Equal, Command::Faster),
+        (KeyCode::Tab, Command::ToggleTurbo),
+        (KeyCode::Period, Command::FrameAdvance),
+        (KeyCode::F1, Command::Reset),
+        (KeyCode::F2, Command::Bind),
+        (KeyCode::F3, Command::BindPad),
+        (KeyCode::F4, Command::NextPalette),
+        (KeyCode::F5, Command::Rotate),
+        (KeyCode::F6, Command::Flip),
+        // For layouts such as Dvorak that bind the period to the keypad.
+        (KeyCode::F7, Command::FrameAdvance),
+        (KeyCode::Up, Command::Up),
+        (KeyCode::Down, Command::Down),
+        (KeyCode::Left, Command::Left),
+        (KeyCode::Right, Command::Right),
+        (KeyCode::Enter, Command::Select),
+    ];
+
+    /// The key for a host key as named in a `Keymap`, if the window can detect it.
+    fn key_code(key: char) -> Option<KeyCode> {
+        let code = match key {
+            '0' => KeyCode::Key0,
+            '1' => KeyCode::Key1,
+            '2' => KeyCode::Key2,
+            '3' => KeyCode::Key3,
+            '4' => KeyCode::Key4,
+            '5' => KeyCode::Key5,
+            '6' => KeyCode::Key6,
+            '7' => KeyCode::Key7,
+            '8' => KeyCode::Key8,
+            '9' => KeyCode::Key9,
+            'a' => KeyCode::A,
+            'b' => KeyCode::B,
+            'c' => KeyCode::C,
+            'd' => KeyCode::D,
+            'e' => KeyCode::E,
+            'f' => KeyCode::F,
+            'g' => KeyCode::G,
+            'h' => KeyCode::H,
+            'i' => KeyCode::I,
+            'j' => KeyCode::J,
+            'k' => KeyCode::K,
+            'l' => KeyCode::L,
+            'm' => KeyCode::M,
+            'n' => KeyCode::N,
+            'o' => KeyCode::O,
+            'p' => KeyCode::P,
+            'q' => KeyCode::Q,
+            'r' => KeyCode::R,
+            's' => KeyCode::S,
+            't' => KeyCode::T,
+            'u' => KeyCode::U,
+            'v' => KeyCode::V,
+            'w' => KeyCode::W,
+            'x' => KeyCode::X,
+            'y' => KeyCode::Y,
+            'z' => KeyCode::Z,
+            ',' => KeyCode::Comma,
+            '.' => KeyCode::Period,
+            ';' => KeyCode::Semicolon,
+            '\'' => KeyCode::Apostrophe,
+            '-' => KeyCode::Minus,
+            '=' => KeyCode::Equal,
+            '/' => KeyCode::Slash,
+            '[' => KeyCode::LeftBracket,
+            ']' => KeyCode::RightBracket,
+            '`' => KeyCode::GraveAccent,
+            '\\' => KeyCode::Backslash,
+            _ => return None,
+        };
+
+        Some(code)
+    }
+}
+
+impl InputSource for MacroquadInput {
+    fn poll(&mut self) -> Input {
+        let mut input = Input {
+            keys: self
+                .keymap
+                .pressed(|host| Self::key_code(host).is_some_and(is_key_down)),
+            ..Default::default()
+        };
+
+        // Keys bound to the CHIP-8 keypad are not also commands.
+        let bound: Vec<KeyCode> = Keymap::BINDABLE
+            .chars()
+            .filter(|&host| self.keymap.key(host).is_some())
+            .filter_map(Self::key_code)
+            .collect();
+        for (key, command) in Self::COMMAND_KEYS {
+            if is_key_pressed(key) && !bound.contains(&key) {
+                input.commands.push(command);
+            }
+        }
+
+        input.typed = Keymap::BINDABLE
+            .chars()
+            .filter(|&host| Self::key_code(host).is_some_and(is_key_pressed))
+            .collect();
+
+        input
+    }
+
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+}
+
+#[derive(Debug)]
+pub struct MacroquadAudio {
+    beep: Sound,
+    beeping: bool,
+    volume: f32,
+}
+
+impl MacroquadAudio {
+    /// Generates the beep from `beeper`.
+    pub async fn new(beeper: Beeper) -> Result<Self> {
+        let mut beeper = beeper;
+        // A whole second holds a whole number of periods, so the loop joins without a click.
+        let mut samples = vec![0.0; beeper.sample_rate as usize];
+        beeper.render(true, &mut samples);
+
+        let mut wav = Vec::new();
+        write_wav(&mut wav, beeper.sample_rate, &samples)?;
+        let beep = load_sound_from_bytes(&wav)
+            .await
+            .map_err(|err| anyhow!("Error loading beep: {err}"))?;
+
+        Ok(Self {
+            beep,
+            beeping: false,
+            volume: 1.0,
+        })
+    }
+}
+
+impl AudioSink for MacroquadAudio {
+    fn set_beeping(&mut self, active: bool) {
+        if active && !self.beeping {
+            play_sound(
+                &self.beep,
+                PlaySoundParams {
+                    looped: true,
+                    volume: self.volume,
+                },
+            );
+        } else if !active && self.beeping {
+            stop_sound(&self.beep);
+        }
+        self.beeping = active;
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        set_sound_volume(&self.beep, self.volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_codes() {
+        assert!(Keymap::BINDABLE
+            .chars()
+            .all(|host| MacroquadInput::key_code(host).is_some()));
+        assert_eq!(None, MacroquadInput::key_code('é'));
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+use chip8::Chip8;
+
+use super::{AudioSink, Clock, Input, InputSource, Speed, VideoSink, View};
+use crate::padmap::Button;
+use crate::render::Orientation;
+use crate::Palette;
+
+/// An owned copy of the last presented view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Screen {
+    /// Pixels row by row, `true` where lit.
+    Game(Vec<bool>),
+    Paused,
+    Menu {
+        cursor: usize,
+    },
+    Binding {
+        key: usize,
+        hosts: Vec<char>,
+    },
+    PadBinding {
+        key: usize,
+        buttons: Vec<Button>,
+    },
+}
+
+/// Keeps the last presented frame instead of drawing it.
+#[derive(Debug, Default)]
+pub struct MemoryVideo {
+    pub screen: Option<Screen>,
+    pub frames: usize,
+    /// Host frames in which the emulator ran.
+    pub emulated: usize,
+    pub speed: Speed,
+    pub palette: Palette,
+    pub orientation: Orientation,
+}
+
+impl VideoSink for MemoryVideo {
+    fn present(&mut self, view: View<'_>) {
+        self.screen = Some(match view {
+            View::Game(chip8) => {
+                let (width, height) = (chip8.platform.video_width, chip8.platform.video_height);
+                Screen::Game(
+                    (0..height)
+                        .flat_map(|y| (0..width).map(move |x| chip8.has_color(x, y)))
+                        .collect(),
+                )
+            }
+            View::Paused(_) => Screen::Paused,
+            View::Menu { cursor, .. } => Screen::Menu { cursor },
+            View::Binding { key, keymap } => Screen::Binding {
+                key,
+                hosts: keymap.hosts(key).to_vec(),
+            },
+            View::PadBinding { key, padmap } => Screen::PadBinding {
+                key,
+                buttons: padmap.buttons(key),
+            },
+        });
+        self.frames += 1;
+    }
+
+    fn frames_ran(&mut self, _chip8: &Chip8) {
+        self.emulated += 1;
+    }
+
+    fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+}
+
+/// Replays queued input, one entry per frame, then reports nothing pressed.
+#[derive(Debug, Default)]
+pub struct MemoryInput {
+    pub script: VecDeque<Input>,
+}
+
+impl MemoryInput {
+    pub fn push(&mut self, input: Input) {
+        self.script.push_back(input);
+    }
+}
+
+impl InputSource for MemoryInput {
+    fn poll(&mut self) -> Input {
+        self.script.pop_front().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryAudio {
+    pub beeping: bool,
+    /// Number of frames the beep was on for.
+    pub beep_frames: usize,
+}
+
+impl AudioSink for MemoryAudio {
+    fn set_beeping(&mut self, active: bool) {
+        self.beeping = active;
+        if active {
+            self.beep_frames += 1;
+        }
+    }
+}
+
+/// Counts frames without waiting, so tests run as fast as possible. Each frame has no time to
+/// spare, so uncapped speed runs a single frame.
+#[derive(Debug, Default)]
+pub struct MemoryClock {
+    pub frames: usize,
+}
+
+impl Clock for MemoryClock {
+    fn wait_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    fn frame_over(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::anyhow;
+use chip8::Chip8;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::keymap::Keymap;
+use crate::padmap::{Button, PadMap};
+use crate::render::Orientation;
+use crate::Palette;
+
+pub use self::macroquad::{
+    ChipsteRS, MacroquadAudio, MacroquadInput, MacroquadVideo, WindowInput, WindowOptions,
+};
+pub use clock::SleepClock;
+#[cfg(feature = "gamepad")]
+pub use gamepad::GamepadInput;
+pub use memory::{MemoryAudio, MemoryClock, MemoryInput, MemoryVideo, Screen};
+pub use terminal::{
+    Glyphs, TerminalBell, TerminalInput, TerminalKeys, TerminalRunner, TerminalSession,
+    TerminalVideo,
+};
+
+mod clock;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod macroquad;
+mod memory;
+mod terminal;
+
+/// What the runner wants shown for the current frame.
+#[derive(Debug)]
+pub enum View<'a> {
+    Game(&'a Chip8),
+    Paused(&'a Chip8),
+    Menu {
+        titles: &'a [String],
+        cursor: usize,
+    },
+    /// Asks for the host keys of a CHIP-8 key.
+    Binding {
+        key: usize,
+        keymap: &'a Keymap,
+    },
+    /// Asks for the gamepad buttons of a CHIP-8 key.
+    PadBinding {
+        key: usize,
+        padmap: &'a PadMap,
+    },
+}
+
+/// Frontend-independent commands, separate from the CHIP-8 keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Leaves the game for the menu, or quits from the menu.
+    Back,
+    Quit,
+    TogglePause,
+    Reset,
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Faster,
+    Slower,
+    /// Switches between normal speed and the turbo speed.
+    ToggleTurbo,
+    /// Runs a single frame while paused.
+    FrameAdvance,
+    /// Switches to the next named palette, saved for the running ROM.
+    NextPalette,
+    /// Turns the display a quarter turn clockwise, saved for the running ROM.
+    Rotate,
+    /// Steps through mirroring the display horizontally, vertically and both, saved for the
+    /// running ROM.
+    Flip,
+    /// Asks for new host keys for each CHIP-8 key in turn.
+    Bind,
+    /// Asks for new gamepad buttons for each CHIP-8 key in turn, for the current ROM.
+    BindPad,
+}
+
+/// How fast the emulator runs relative to one frame per 60 Hz host frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Speed {
+    /// One frame every `n` host frames.
+    Slow(u32),
+    #[default]
+    Normal,
+    /// `n` frames every host frame.
+    Turbo(u32),
+    /// As many frames as fit in each host frame.
+    Uncapped,
+}
+
+impl Speed {
+    /// The speeds stepped through by `Command::Faster` and `Command::Slower`, slowest first.
+    pub const STEPS: [Self; 7] = [
+        Self::Slow(4),
+        Self::Slow(2),
+        Self::Normal,
+        Self::Turbo(2),
+        Self::Turbo(4),
+        Self::Turbo(8),
+        Self::Uncapped,
+    ];
+
+    #[must_use]
+    pub fn faster(self) -> Self {
+        Self::STEPS[(self.step() + 1).min(Self::STEPS.len() - 1)]
+    }
+
+    #[must_use]
+    pub fn slower(self) -> Self {
+        Self::STEPS[self.step().saturating_sub(1)]
+    }
+
+    fn step(self) -> usize {
+        Self::STEPS
+            .iter()
+            .position(|&speed| speed == self)
+            .unwrap_or(2)
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Slow(n) => write!(f, "{}×", 1.0 / *n as f32),
+            Self::Normal => write!(f, "1×"),
+            Self::Turbo(n) => write!(f, "{n}×"),
+            Self::Uncapped => write!(f, "Uncapped"),
+        }
+    }
+}
+
+impl FromStr for Speed {
+    type Err = anyhow::Error;
+
+    /// Parses `uncapped` or a multiple such as `4`, `2x` or `0.25x`.
+    fn from_str(speed: &str) -> anyhow::Result<Self> {
+        if speed.eq_ignore_ascii_case("uncapped") {
+            return Ok(Self::Uncapped);
+        }
+
+        let invalid =
+            || anyhow!("Invalid speed {speed:?}, expected uncapped or a multiple like 4x");
+        let multiple: f32 = speed
+            .trim_end_matches(['x', '×'])
+            .parse()
+            .map_err(|_| invalid())?;
+        let whole = |value: f32| (value.fract() == 0.0 && value <= 64.0).then_some(value as u32);
+
+        match multiple {
+            1.0 => Ok(Self::Normal),
+            _ if multiple > 1.0 => whole(multiple).map(Self::Turbo).ok_or_else(invalid),
+            _ if multiple > 0.0 => whole(1.0 / multiple).map(Self::Slow).ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Input gathered for one frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Input {
+    /// CHIP-8 keys held down, indexed by key value.
+    pub keys: [bool; 16],
+    /// Commands pressed since the previous frame.
+    pub commands: Vec<Command>,
+    /// Host keys that can be bound to CHIP-8 keys, pressed since the previous frame.
+    pub typed: Vec<char>,
+    /// Gamepad buttons that can be bound to CHIP-8 keys, pressed since the previous frame.
+    pub buttons: Vec<Button>,
+}
+
+pub trait VideoSink {
+    fn present(&mut self, view: View<'_>);
+
+    /// Called after the emulator ran one or more frames this host frame, before they are
+    /// presented. Paused and menu frames are presented without it.
+    fn frames_ran(&mut self, _chip8: &Chip8) {}
+
+    /// Called when the emulation speed changes, for an on-screen indicator.
+    fn set_speed(&mut self, _speed: Speed) {}
+
+    fn set_palette(&mut self, _palette: Palette) {}
+
+    /// Sets how much of its brightness an unlit pixel keeps each frame, 0.0 for none.
+    fn set_phosphor(&mut self, _decay: f32) {}
+
+    /// Sets how the display is turned and mirrored.
+    fn set_orientation(&mut self, _orientation: Orientation) {}
+}
+
+pub trait InputSource {
+    fn poll(&mut self) -> Input;
+
+    fn set_keymap(&mut self, _keymap: Keymap) {}
+
+    fn set_padmap(&mut self, _padmap: PadMap) {}
+}
+
+pub trait AudioSink {
+    /// Called every frame with whether the sound timer is active.
+    fn set_beeping(&mut self, active: bool);
+
+    /// Sets the beep volume, from 0.0 for silence to 1.0.
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+pub trait Clock {
+    /// Blocks until the current 60 Hz frame is over.
+    fn wait_frame(&mut self);
+
+    /// Whether the current 60 Hz frame has no time left to run more emulation in.
+    fn frame_over(&self) -> bool;
+}
@@ -0,0 +1,530 @@
+use crossterm::cursor::{Hide, MoveTo, MoveToNextLine, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{
+    self, supports_keyboard_enhancement, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+
+use chip8::{Chip8, State};
+
+#[cfg(feature = "gamepad")]
+use super::GamepadInput;
+use super::{AudioSink, Command, Input, InputSource, SleepClock, Speed, VideoSink, View};
+use crate::keymap::Keymap;
+use crate::render::Orientation;
+use crate::Runner;
+
+/// The emulator drawn with text in a terminal.
+pub type TerminalRunner = Runner<TerminalVideo, TerminalKeys, TerminalBell, SleepClock>;
+
+/// Keyboard input, with gamepads added by the `gamepad` feature.
+#[cfg(feature = "gamepad")]
+pub type TerminalKeys = GamepadInput<TerminalInput>;
+#[cfg(not(feature = "gamepad"))]
+pub type TerminalKeys = TerminalInput;
+
+impl TerminalRunner {
+    pub fn terminal(session: &TerminalSession, glyphs: Glyphs) -> Self {
+        let input = TerminalInput::new(session.reports_releases);
+        #[cfg(feature = "gamepad")]
+        let input = GamepadInput::new(input);
+
+        Runner::new(
+            TerminalVideo::new(glyphs),
+            input,
+            TerminalBell::default(),
+            SleepClock::new(),
+        )
+    }
+}
+
+/// Puts the terminal into raw mode on an alternate screen, restoring it when dropped.
+#[derive(Debug)]
+pub struct TerminalSession {
+    reports_releases: bool,
+}
+
+impl TerminalSession {
+    pub fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+        // Only some terminals report key releases; the others get them emulated.
+        let reports_releases = supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self { reports_releases })
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        if self.reports_releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// How pixels are packed into characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Glyphs {
+    /// One column by two rows per character.
+    #[default]
+    HalfBlock,
+    /// Two columns by four rows per character.
+    Braille,
+}
+
+impl Glyphs {
+    /// Renders a `width` by `height` image into lines of text.
+    pub fn render(self, width: u16, height: u16, lit: impl Fn(u16, u16) -> bool) -> Vec<String> {
+        let pixel = |x: u16, y: u16| x < width && y < height && lit(x, y);
+
+        match self {
+            Self::HalfBlock => (0..height.div_ceil(2))
+                .map(|row| {
+                    (0..width)
+                        .map(|x| match (pixel(x, row * 2), pixel(x, row * 2 + 1)) {
+                            (false, false) => ' ',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (true, true) => '█',
+                        })
+                        .collect()
+                })
+                .collect(),
+            Self::Braille => {
+                // Dot numbering of the Unicode braille block, by row then column.
+                const DOTS: [[u32; 2]; 4] =
+                    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+                (0..height.div_ceil(4))
+                    .map(|row| {
+                        (0..width.div_ceil(2))
+                            .map(|column| {
+                                let mut bits = 0;
+                                for (dy, dots) in DOTS.iter().enumerate() {
+                                    for (dx, dot) in dots.iter().enumerate() {
+                                        if pixel(column * 2 + dx as u16, row * 4 + dy as u16) {
+                                            bits |= dot;
+                                        }
+                                    }
+                                }
+                                char::from_u32(0x2800 + bits).unwrap_or(' ')
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TerminalVideo {
+    glyphs: Glyphs,
+    speed: Speed,
+    orientation: Orientation,
+    out: Stdout,
+}
+
+impl TerminalVideo {
+    const MENU_ROWS: usize = 20;
+
+    pub fn new(glyphs: Glyphs) -> Self {
+        Self {
+            glyphs,
+            speed: Speed::Normal,
+            orientation: Orientation::default(),
+            out: io::stdout(),
+        }
+    }
+
+    fn game_lines(&self, chip8: &Chip8) -> Vec<String> {
+        let (video_width, video_height) = (chip8.platform.video_width, chip8.platform.video_height);
+        let (width, height) = self.orientation.size(video_width, video_height);
+        let screen = self.glyphs.render(width, height, |x, y| {
+            let (x, y) = self.orientation.source(x, y, video_width, video_height);
+            chip8.has_color(x, y)
+        });
+        let panel = Self::panel(chip8, self.speed);
+        let width = screen.first().map_or(0, |line| line.chars().count());
+
+        let mut lines = vec![format!("┌{}┐", "─".repeat(width))];
+        for i in 0..screen.len().max(panel.len()) {
+            let screen_line = screen
+                .get(i)
+                .map_or_else(|| " ".repeat(width + 2), |line| format!("│{line}│"));
+            let panel_line = panel.get(i).map_or("", String::as_str);
+            lines.push(format!("{screen_line}  {panel_line}"));
+        }
+        lines.push(format!("└{}┘", "─".repeat(width)));
+
+        lines
+    }
+
+    fn panel(chip8: &Chip8, speed: Speed) -> Vec<String> {
+        let registers = chip8.registers();
+        let mut panel = vec![
+            format!("PC {:#06x}  I {:#06x}", registers.pc, registers.i),
+            format!(
+                "SP {:<2}  DT {:<3}  ST {:<3}",
+                registers.sp, registers.dt, registers.st
+            ),
+            String::new(),
+        ];
+        for row in registers.v.chunks(4).enumerate() {
+            let (row, values) = row;
+            panel.push(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| format!("V{:X} {value:02x}", row * 4 + i))
+                    .collect::<Vec<_>>()
+                    .join("  "),
+            );
+        }
+        panel.push(String::new());
+        let status = match (chip8.state, chip8.halt()) {
+            (State::Paused, _) => "PAUSED".to_string(),
+            (State::Finished, Some(halt)) => halt.to_string(),
+            (State::Finished, None) => "FINISHED".to_string(),
+            _ => String::new(),
+        };
+        panel.push(if speed == Speed::Normal {
+            status
+        } else {
+            format!("{status}  {speed}").trim_start().to_string()
+        });
+        panel.push("Space pause  F1 reset  F2 keys  F3 pad".to_string());
+        panel.push("-/= speed  Tab turbo  ./F7 advance".to_string());
+        panel.push("F5 rotate  F6 flip".to_string());
+        panel.push("Esc back  Ctrl+C quit".to_string());
+
+        panel
+    }
+
+    fn binding_lines(key: usize, device: &str, bound: &[String]) -> Vec<String> {
+        vec![
+            format!("Press {device} for {key:X}"),
+            format!("Bound: {}", bound.join(" ")),
+            String::new(),
+            "Enter next key  Esc done".to_string(),
+        ]
+    }
+
+    fn menu_lines(titles: &[String], cursor: usize) -> Vec<String> {
+        let mut lines = vec![
+            "Up/Down select  Enter load  Esc quit".to_string(),
+            String::new(),
+        ];
+        lines.extend(
+            titles
+                .iter()
+                .enumerate()
+                .skip(cursor)
+                .take(Self::MENU_ROWS)
+                .map(|(i, title)| {
+                    let marker = if i == cursor { '>' } else { ' ' };
+                    format!("{marker} {}/{} {title}", i + 1, titles.len())
+                }),
+        );
+
+        lines
+    }
+
+    fn draw(&mut self, lines: &[String]) -> io::Result<()> {
+        queue!(self.out, MoveTo(0, 0))?;
+        for line in lines {
+            queue!(
+                self.out,
+                Print(line),
+                Clear(ClearType::UntilNewLine),
+                MoveToNextLine(1)
+            )?;
+        }
+        queue!(self.out, Clear(ClearType::FromCursorDown))?;
+        self.out.flush()
+    }
+}
+
+impl VideoSink for TerminalVideo {
+    fn present(&mut self, view: View<'_>) {
+        let lines = match view {
+            View::Game(chip8) | View::Paused(chip8) => self.game_lines(chip8),
+            View::Menu { titles, cursor } => Self::menu_lines(titles, cursor),
+            View::Binding { key, keymap } => {
+                let hosts: Vec<String> = keymap.hosts(key).iter().map(char::to_string).collect();
+                Self::binding_lines(key, "keys", &hosts)
+            }
+            View::PadBinding { key, padmap } => {
+                let buttons: Vec<String> = padmap
+                    .buttons(key)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                Self::binding_lines(key, "buttons", &buttons)
+            }
+        };
+
+        // A failed write only loses this frame; the next one redraws everything.
+        let _ = self.draw(&lines);
+    }
+
+    fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+}
+
+/// Tracks which keys are held when the terminal only reports presses.
+#[derive(Debug, Default)]
+struct KeyHold {
+    reports_releases: bool,
+    held: [bool; 16],
+    frames_left: [u8; 16],
+}
+
+impl KeyHold {
+    /// Frames a key stays down after its last press when releases are not reported, long enough to
+    /// bridge the gaps between auto-repeated presses.
+    const HOLD_FRAMES: u8 = 8;
+
+    fn press(&mut self, key: usize) {
+        if self.reports_releases {
+            self.held[key] = true;
+        } else {
+            self.frames_left[key] = Self::HOLD_FRAMES;
+        }
+    }
+
+    fn release(&mut self, key: usize) {
+        self.held[key] = false;
+        self.frames_left[key] = 0;
+    }
+
+    /// Keys down this frame, counting emulated holds down by one frame.
+    fn tick(&mut self) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for (i, key) in keys.iter_mut().enumerate() {
+            *key = self.held[i] || self.frames_left[i] > 0;
+            self.frames_left[i] = self.frames_left[i].saturating_sub(1);
+        }
+
+        keys
+    }
+}
+
+#[derive(Debug)]
+pub struct TerminalInput {
+    hold: KeyHold,
+    keymap: Keymap,
+    typed: Vec<char>,
+}
+
+impl TerminalInput {
+    pub fn new(reports_releases: bool) -> Self {
+        Self {
+            hold: KeyHold {
+                reports_releases,
+                ..Default::default()
+            },
+            keymap: Keymap::default(),
+            typed: Vec::new(),
+        }
+    }
+
+    fn handle(
+        &mut self,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+        kind: KeyEventKind,
+    ) -> Option<Command> {
+        if kind == KeyEventKind::Release {
+            if let Some(key) = Self::char(code).and_then(|c| self.keymap.key(c)) {
+                self.hold.release(key);
+            }
+            return None;
+        }
+
+        if let Some(c) = Self::char(code).filter(|&c| c != ' ') {
+            if kind == KeyEventKind::Press && !modifiers.contains(KeyModifiers::CONTROL) {
+                self.typed.push(c.to_ascii_lowercase());
+            }
+        }
+
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Some(Command::Quit),
+            // Keys bound to the CHIP-8 keypad are not also commands.
+            KeyCode::Char(c) if self.keymap.key(c).is_some() => {
+                self.hold.press(self.keymap.key(c)?);
+                None
+            }
+            KeyCode::Char(' ') if kind == KeyEventKind::Press => Some(Command::TogglePause),
+            KeyCode::Char('-') if kind == KeyEventKind::Press => Some(Command::Slower),
+            KeyCode::Char('=' | '+') if kind == KeyEventKind::Press => Some(Command::Faster),
+            KeyCode::Char('.') if kind == KeyEventKind::Press => Some(Command::FrameAdvance),
+            KeyCode::Tab if kind == KeyEventKind::Press => Some(Command::ToggleTurbo),
+            KeyCode::Esc if kind == KeyEventKind::Press => Some(Command::Back),
+            KeyCode::F(1) if kind == KeyEventKind::Press => Some(Command::Reset),
+            KeyCode::F(2) if kind == KeyEventKind::Press => Some(Command::Bind),
+            KeyCode::F(3) if kind == KeyEventKind::Press => Some(Command::BindPad),
+            KeyCode::F(5) if kind == KeyEventKind::Press => Some(Command::Rotate),
+            KeyCode::F(6) if kind == KeyEventKind::Press => Some(Command::Flip),
+            KeyCode::F(7) if kind == KeyEventKind::Press => Some(Command::FrameAdvance),
+            KeyCode::Up => Some(Command::Up),
+            KeyCode::Down => Some(Command::Down),
+            KeyCode::Left => Some(Command::Left),
+            KeyCode::Right => Some(Command::Right),
+            KeyCode::Enter if kind == KeyEventKind::Press => Some(Command::Select),
+            _ => None,
+        }
+    }
+
+    fn char(code: KeyCode) -> Option<char> {
+        match code {
+            KeyCode::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll(&mut self) -> Input {
+        let mut input = Input::default();
+
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                if let Some(command) = self.handle(key.code, key.modifiers, key.kind) {
+                    input.commands.push(command);
+                }
+            }
+        }
+        input.keys = self.hold.tick();
+        input.typed = std::mem::take(&mut self.typed);
+
+        input
+    }
+
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+}
+
+/// Rings the terminal bell each time the sound timer starts, unless the volume is zero.
+#[derive(Debug, Default)]
+pub struct TerminalBell {
+    beeping: bool,
+    muted: bool,
+}
+
+impl AudioSink for TerminalBell {
+    fn set_beeping(&mut self, active: bool) {
+        if active && !self.beeping && !self.muted {
+            let mut out = io::stdout();
+            let _ = out.write_all(b"\x07").and_then(|()| out.flush());
+        }
+        self.beeping = active;
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.muted = volume <= 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_block() {
+        let lines = Glyphs::HalfBlock.render(4, 3, |x, y| x == y || x == 3);
+
+        assert_eq!(vec!["▀▄ █", "  ▀▀"], lines);
+    }
+
+    #[test]
+    fn test_braille() {
+        let lines = Glyphs::Braille.render(3, 4, |x, y| x == 0 || y == 3);
+
+        assert_eq!(vec!["⣇⡀"], lines);
+    }
+
+    #[test]
+    fn test_emulated_release() {
+        let mut input = TerminalInput::new(false);
+
+        assert_eq!(
+            None,
+            input.handle(KeyCode::Char('W'), KeyModifiers::SHIFT, KeyEventKind::Press)
+        );
+        for _ in 0..KeyHold::HOLD_FRAMES {
+            assert!(input.hold.tick()[5]);
+        }
+        assert!(!input.hold.tick()[5]);
+    }
+
+    #[test]
+    fn test_reported_release() {
+        let mut input = TerminalInput::new(true);
+
+        input.handle(KeyCode::Char('x'), KeyModifiers::NONE, KeyEventKind::Press);
+        for _ in 0..2 * KeyHold::HOLD_FRAMES {
+            assert!(input.hold.tick()[0]);
+        }
+        input.handle(
+            KeyCode::Char('x'),
+            KeyModifiers::NONE,
+            KeyEventKind::Release,
+        );
+        assert!(!input.hold.tick()[0]);
+
+        assert_eq!(
+            Some(Command::Quit),
+            input.handle(
+                KeyCode::Char('c'),
+                KeyModifiers::CONTROL,
+                KeyEventKind::Press
+            )
+        );
+        assert_eq!(
+            Some(Command::TogglePause),
+            input.handle(KeyCode::Char(' '), KeyModifiers::NONE, KeyEventKind::Press)
+        );
+    }
+
+    #[test]
+    fn test_keymap() {
+        let mut input = TerminalInput::new(true);
+        input.set_keymap(Keymap::layout("dvorak").unwrap());
+
+        // Bound keys press the keypad instead of running their command.
+        assert_eq!(
+            None,
+            input.handle(KeyCode::Char('.'), KeyModifiers::NONE, KeyEventKind::Press)
+        );
+        assert!(input.hold.tick()[6]);
+        assert_eq!(
+            Some(Command::Slower),
+            input.handle(KeyCode::Char('-'), KeyModifiers::NONE, KeyEventKind::Press)
+        );
+        assert_eq!(vec!['.', '-'], input.typed);
+    }
+}
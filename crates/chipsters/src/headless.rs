@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chip8::audio::{write_wav, Beeper};
+use chip8::Chip8;
+
+use crate::config::Settings;
+use crate::phosphor::Phosphor;
+use crate::render::{Frame, RenderOptions};
+
+/// Runs the ROM on `chip8` without a window for `frames` 60 Hz frames and saves the display to
+/// `png_path`, in the palette, phosphor persistence and orientation of `settings`, filtered and
+/// then scaled up `scale` times.
+pub fn screenshot(
+    mut chip8: Chip8,
+    rom_path: &Path,
+    png_path: &Path,
+    frames: usize,
+    settings: &Settings,
+    options: &RenderOptions,
+    scale: usize,
+) -> Result<()> {
+    chip8
+        .load_rom(rom_path)
+        .map_err(|err| anyhow!("Error loading ROM at path {}: {}", rom_path.display(), err))?;
+
+    let mut phosphor = Phosphor::new(settings.phosphor.unwrap_or(0.0));
+    for _ in 0..frames.max(1) {
+        chip8.run_frame()?;
+        phosphor.update(chip8.framebuffer());
+    }
+
+    let palette = settings.palette.unwrap_or_default();
+    let frame = Frame::new(
+        usize::from(chip8.platform.video_width),
+        usize::from(chip8.platform.video_height),
+        phosphor.colors(&palette),
+    );
+    let frame = options
+        .render(&frame.orient(settings.orientation()))
+        .scale(scale);
+    image::save_buffer(
+        png_path,
+        frame.as_bytes(),
+        frame.width as u32,
+        frame.height as u32,
+        image::ColorType::Rgba8,
+    )
+    .map_err(|err| anyhow!("Error saving {}: {}", png_path.display(), err))?;
+
+    Ok(())
+}
+
+/// Runs the ROM on `chip8` without a window for `frames` 60 Hz frames and writes the beeper
+/// output to `wav_path`.
+pub fn record_wav(mut chip8: Chip8, rom_path: &Path, wav_path: &Path, frames: usize) -> Result<()> {
+    chip8
+        .load_rom(rom_path)
+        .map_err(|err| anyhow!("Error loading ROM at path {}: {}", rom_path.display(), err))?;
+
+    let mut beeper = Beeper::default();
+    let samples_per_frame = beeper.samples_per_frame();
+    let mut samples = vec![0.0; frames * samples_per_frame];
+
+    for frame in samples.chunks_mut(samples_per_frame) {
+        chip8.run_frame()?;
+        beeper.render(chip8.sound_active(), frame);
+    }
+
+    let file = File::create(wav_path)
+        .map_err(|err| anyhow!("Error creating {}: {}", wav_path.display(), err))?;
+    let mut writer = BufWriter::new(file);
+    write_wav(&mut writer, beeper.sample_rate, &samples)?;
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Which host keys press each key of the CHIP-8 keypad.
+
+/// Layouts by name, each giving the host keys for CHIP-8 keys 0 to F. The QWERTY layout puts the
+/// COSMAC VIP hex keypad on the left of the keyboard:
+///
+/// ```text
+/// 1 2 3 C    1 2 3 4
+/// 4 5 6 D    Q W E R
+/// 7 8 9 E    A S D F
+/// A 0 B F    Z X C V
+/// ```
+///
+/// AZERTY and Dvorak use the same physical keys, as far as they are in `Keymap::BINDABLE`, so the
+/// AZERTY top row is the digits 1 to 4. The DREAM 6800 keypad runs 0 to F in rows on the same keys.
+const LAYOUTS: [(&str, [&str; 16]); 4] = [
+    (
+        "qwerty",
+        [
+            "x", "1", "2", "3", "q", "w", "e", "a", "s", "d", "z", "c", "4", "r", "f", "v",
+        ],
+    ),
+    (
+        "azerty",
+        [
+            "x", "1", "2", "3", "a", "z", "e", "q", "s", "d", "w", "c", "4", "r", "f", "v",
+        ],
+    ),
+    (
+        "dvorak",
+        [
+            "q", "1", "2", "3", "'", ",", ".", "a", "o", "e", ";", "j", "4", "p", "u", "k",
+        ],
+    ),
+    (
+        "dream-6800",
+        [
+            "1", "2", "3", "4", "q", "w", "e", "r", "a", "s", "d", "f", "z", "x", "c", "v",
+        ],
+    ),
+];
+
+/// The host keys bound to each CHIP-8 key. A CHIP-8 key can have several host keys, but a host
+/// key presses only one CHIP-8 key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    hosts: [Vec<char>; 16],
+}
+
+impl Keymap {
+    /// Host keys that every frontend can detect, and so the only ones the presets use.
+    pub const BINDABLE: &'static str = "0123456789abcdefghijklmnopqrstuvwxyz,.;'-=/[]`\\";
+
+    /// Names of the preset layouts.
+    pub const LAYOUTS: [&'static str; 4] = ["qwerty", "azerty", "dvorak", "dream-6800"];
+
+    /// The preset layout called `name`, ignoring case.
+    pub fn layout(name: &str) -> Option<Self> {
+        LAYOUTS
+            .iter()
+            .find(|(layout, _)| layout.eq_ignore_ascii_case(name))
+            .map(|(_, keys)| Self::from_keys(keys))
+    }
+
+    fn from_keys(keys: &[&str; 16]) -> Self {
+        Self {
+            hosts: keys.map(|hosts| hosts.chars().collect()),
+        }
+    }
+
+    pub fn hosts(&self, key: usize) -> &[char] {
+        &self.hosts[key]
+    }
+
+    /// The CHIP-8 key that `host` presses.
+    pub fn key(&self, host: char) -> Option<usize> {
+        let host = host.to_ascii_lowercase();
+        self.hosts.iter().position(|hosts| hosts.contains(&host))
+    }
+
+    /// Adds `host` to the keys for `key`, taking it away from any other key.
+    pub fn bind(&mut self, key: usize, host: char) {
+        let host = host.to_ascii_lowercase();
+        for hosts in &mut self.hosts {
+            hosts.retain(|&bound| bound != host);
+        }
+        self.hosts[key].push(host);
+    }
+
+    pub fn clear(&mut self, key: usize) {
+        self.hosts[key].clear();
+    }
+
+    /// CHIP-8 keys held down, given which host keys are down.
+    pub fn pressed(&self, down: impl Fn(char) -> bool) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for (key, hosts) in keys.iter_mut().zip(&self.hosts) {
+            *key = hosts.iter().any(|&host| down(host));
+        }
+
+        keys
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_keys(&LAYOUTS[0].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts() {
+        for name in Keymap::LAYOUTS {
+            let keymap = Keymap::layout(name).unwrap();
+            assert!((0..16).all(|key| !keymap.hosts(key).is_empty()));
+            assert!((0..16)
+                .flat_map(|key| keymap.hosts(key))
+                .all(|&host| Keymap::BINDABLE.contains(host)));
+        }
+
+        let dream = Keymap::layout("DREAM-6800").unwrap();
+        assert_eq!(Some(0), dream.key('1'));
+        assert_eq!(Some(0xF), dream.key('V'));
+        assert_eq!(Some(1), Keymap::layout("azerty").unwrap().key('1'));
+        assert!(Keymap::layout("colemak").is_none());
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut keymap = Keymap::default();
+        keymap.bind(5, 'i');
+        keymap.bind(5, 'Q');
+
+        assert_eq!(&['w', 'i', 'q'], keymap.hosts(5));
+        assert!(keymap.hosts(4).is_empty());
+        assert_eq!(Some(5), keymap.key('q'));
+
+        let keys = keymap.pressed(|host| host == 'i');
+        assert!(keys[5]);
+        assert_eq!(1, keys.iter().filter(|&&down| down).count());
+    }
+}
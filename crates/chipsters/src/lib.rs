@@ -1,3 +1,14 @@
-pub use chipsters::ChipsteRS;
+pub use frontend::ChipsteRS;
+pub use palette::Palette;
+pub use runner::{Flow, Runner};
 
-mod chipsters;
+pub mod frontend;
+pub mod config;
+pub mod headless;
+pub mod keymap;
+pub mod padmap;
+pub mod palette;
+pub mod phosphor;
+pub mod render;
+mod roms;
+mod runner;
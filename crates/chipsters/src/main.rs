@@ -1,25 +1,207 @@
 use anyhow::{anyhow, Result};
-use chipsters::ChipsteRS;
-use std::env;
+use chip8::analysis::Analysis;
+use chip8::decompiler::decompile;
+use chip8::{lint, Chip8, State};
+use chipsters::config::{Config, SavedSettings};
+use chipsters::frontend::{
+    AudioSink, Clock, Glyphs, InputSource, TerminalRunner, TerminalSession, VideoSink,
+    WindowOptions,
+};
+use chipsters::padmap::PadMaps;
+use chipsters::render::RenderOptions;
+use chipsters::{headless, ChipsteRS, Flow, Runner};
+use clap::Parser;
+use macroquad::window::next_frame;
+use std::fs;
 use std::path::Path;
+use std::process::exit;
 
-#[macroquad::main("ChipsteRS")]
-async fn main() -> Result<()> {
+use cli::{Cli, Tool};
+
+mod cli;
+
+fn main() {
     env_logger::init();
 
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        return Err(anyhow!("Usage: chipsters <rom_path>"));
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("{err}");
+        exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config = Config::load(cli.machine.config.as_deref())?;
+    if let Some(tool) = &cli.tool {
+        return run_tool(tool, &cli, &config);
+    }
+    let rom_path = cli.rom_path.clone().ok_or(anyhow!("No ROM path given"))?;
+    let saved = match SavedSettings::default_path() {
+        Some(path) => SavedSettings::load(&path)?,
+        None => SavedSettings::default(),
+    };
+
+    if cli.terminal {
+        let glyphs = if cli.braille {
+            Glyphs::Braille
+        } else {
+            Glyphs::HalfBlock
+        };
+        return run_terminal(&cli, config, saved, &rom_path, glyphs);
+    }
+
+    // A single ROM opens the window at its own resolution and orientation.
+    let mut settings = match fs::read(&rom_path) {
+        Ok(rom) if rom_path.is_file() => {
+            config.saved_rom_settings(&saved, &file_name(&rom_path), &rom)
+        }
+        _ => config.settings.clone(),
+    };
+    settings.merge(&cli.settings());
+    let platform = settings.platform()?;
+    let options = WindowOptions {
+        resolution: (platform.video_width, platform.video_height),
+        scale: cli.scale.or(config.window.scale),
+        size: config
+            .window
+            .width
+            .zip(config.window.height)
+            .unwrap_or(WindowOptions::DEFAULT_SIZE),
+        fullscreen: cli.fullscreen || config.window.fullscreen,
+        palette: settings.palette.unwrap_or_default(),
+        render: cli.render_options(&config.window),
+        orientation: settings.orientation(),
+    };
+    macroquad::Window::new("ChipsteRS", async move {
+        if let Err(err) = run_window(&cli, config, saved, &rom_path, options).await {
+            eprintln!("{err}");
+            exit(1);
+        }
+    });
+
+    Ok(())
+}
+
+fn run_tool(tool: &Tool, cli: &Cli, config: &Config) -> Result<()> {
+    let rom_path = match tool {
+        Tool::Wav { rom_path, .. }
+        | Tool::Screenshot { rom_path, .. }
+        | Tool::Disasm { rom_path }
+        | Tool::Decompile { rom_path }
+        | Tool::Lint { rom_path, .. } => rom_path,
+    };
+    let rom = fs::read(rom_path)
+        .map_err(|err| anyhow!("Error reading ROM at path {}: {}", rom_path.display(), err))?;
+    let mut settings = config.rom_settings(&file_name(rom_path), &rom);
+    settings.merge(&cli.settings());
+    let mut chip8 = Chip8::new(settings.platform()?);
+    if let Some(seed) = cli.machine.seed {
+        chip8.set_seed(seed);
+    }
+
+    match tool {
+        Tool::Wav {
+            wav_path, frames, ..
+        } => headless::record_wav(chip8, rom_path, wav_path, *frames)?,
+        Tool::Screenshot {
+            png_path,
+            frames,
+            palette,
+            scale,
+            filter,
+            scanlines,
+            ..
+        } => {
+            settings.palette = palette.or(settings.palette);
+            let options = RenderOptions {
+                filter: filter.or(config.window.filter).unwrap_or_default(),
+                scanlines: *scanlines || config.window.scanlines,
+                ..Default::default()
+            };
+            headless::screenshot(
+                chip8,
+                rom_path,
+                png_path,
+                *frames,
+                &settings,
+                &options,
+                *scale as usize,
+            )?;
+        }
+        Tool::Disasm { .. } => print!("{}", Analysis::new(&rom).disassembly()),
+        Tool::Decompile { .. } => print!("{}", decompile(&rom)),
+        Tool::Lint { cycles, .. } => print!("{}", lint(&rom, &chip8.platform, *cycles)),
     }
 
-    let rom_path = Path::new(&args[1]);
-    let mut chipsters = ChipsteRS::default();
-    chipsters.load(rom_path)
-        .map_err(|err| anyhow!(err))?;
+    Ok(())
+}
+
+fn file_name(rom_path: &Path) -> String {
+    rom_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
 
-    loop {
-        chipsters.handle_input()?;
-        chipsters.update()?;
-        chipsters.draw().await?;
+/// Applies the config, saved settings and options to a runner, then loads the ROM or menu.
+fn start<V, I, A, C>(
+    runner: &mut Runner<V, I, A, C>,
+    cli: &Cli,
+    config: Config,
+    saved: SavedSettings,
+    rom_path: &Path,
+) -> Result<()>
+where
+    V: VideoSink,
+    I: InputSource,
+    A: AudioSink,
+    C: Clock,
+{
+    runner.set_saved(saved);
+    runner.configure(config, cli.settings())?;
+    runner.set_padmaps(match PadMaps::default_path() {
+        Some(path) => PadMaps::load(&path)?,
+        None => PadMaps::built_in()?,
+    });
+    if let Some(seed) = cli.machine.seed {
+        runner.chip8.set_seed(seed);
+    }
+    runner.load(rom_path)?;
+    if cli.paused && runner.chip8.state == State::Running {
+        runner.chip8.state = State::Paused;
     }
-}
\ No newline at end of file
+
+    Ok(())
+}
+
+async fn run_window(
+    cli: &Cli,
+    config: Config,
+    saved: SavedSettings,
+    rom_path: &Path,
+    options: WindowOptions,
+) -> Result<()> {
+    let mut chipsters = ChipsteRS::window(options).await?;
+    start(&mut chipsters, cli, config, saved, rom_path)?;
+
+    while chipsters.frame()? == Flow::Continue {
+        next_frame().await;
+    }
+
+    Ok(())
+}
+
+fn run_terminal(
+    cli: &Cli,
+    config: Config,
+    saved: SavedSettings,
+    rom_path: &Path,
+    glyphs: Glyphs,
+) -> Result<()> {
+    let session = TerminalSession::new()?;
+    let mut chipsters = TerminalRunner::terminal(&session, glyphs);
+    start(&mut chipsters, cli, config, saved, rom_path)?;
+
+    while chipsters.frame()? == Flow::Continue {}
+
+    Ok(())
+}
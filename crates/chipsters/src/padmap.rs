@@ -0,0 +1,285 @@
+//! Which CHIP-8 key each gamepad button presses, per ROM.
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::config::rom_hash;
+
+/// The mappings shipped with chipsters, which a user's mapping file adds to.
+const BUILT_IN: &str = include_str!("../gamepad.toml");
+
+/// Gamepad buttons that can press CHIP-8 keys. Start and select are kept for pausing, loading
+/// and going back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl Button {
+    pub const ALL: [Self; 12] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::South,
+        Self::East,
+        Self::West,
+        Self::North,
+        Self::LeftBumper,
+        Self::RightBumper,
+        Self::LeftTrigger,
+        Self::RightTrigger,
+    ];
+
+    /// Name used in mapping files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::South => "south",
+            Self::East => "east",
+            Self::West => "west",
+            Self::North => "north",
+            Self::LeftBumper => "left-bumper",
+            Self::RightBumper => "right-bumper",
+            Self::LeftTrigger => "left-trigger",
+            Self::RightTrigger => "right-trigger",
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Button {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|button| button.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("Unknown gamepad button {name:?}"))
+    }
+}
+
+/// The CHIP-8 key pressed by each button. Several buttons can press the same key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PadMap {
+    keys: BTreeMap<Button, usize>,
+}
+
+impl PadMap {
+    pub fn key(&self, button: Button) -> Option<usize> {
+        self.keys.get(&button).copied()
+    }
+
+    /// Buttons that press `key`.
+    pub fn buttons(&self, key: usize) -> Vec<Button> {
+        self.keys
+            .iter()
+            .filter(|(_, &bound)| bound == key)
+            .map(|(&button, _)| button)
+            .collect()
+    }
+
+    /// Makes `button` press `key`, instead of any key it pressed before.
+    pub fn bind(&mut self, button: Button, key: usize) {
+        self.keys.insert(button, key);
+    }
+
+    pub fn clear(&mut self, key: usize) {
+        self.keys.retain(|_, &mut bound| bound != key);
+    }
+
+    /// CHIP-8 keys held down, given the buttons held down.
+    pub fn pressed(&self, buttons: impl IntoIterator<Item = Button>) -> [bool; 16] {
+        let mut keys = [false; 16];
+        for key in buttons.into_iter().filter_map(|button| self.key(button)) {
+            keys[key] = true;
+        }
+
+        keys
+    }
+
+    fn parse(table: &BTreeMap<String, String>) -> Result<Self> {
+        let mut padmap = Self::default();
+        for (button, key) in table {
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < 16)
+                .ok_or_else(|| anyhow!("Invalid CHIP-8 key {key:?}, expected 0 to F"))?;
+            padmap.bind(button.parse()?, usize::from(key));
+        }
+
+        Ok(padmap)
+    }
+
+    fn table(&self) -> BTreeMap<String, String> {
+        self.keys
+            .iter()
+            .map(|(button, key)| (button.name().to_string(), format!("{key:X}")))
+            .collect()
+    }
+}
+
+/// A mapping file: a default mapping and mappings for single ROMs.
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct PadFile {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    default: BTreeMap<String, String>,
+    roms: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// The built-in mappings with the user's on top, keyed by ROM file name or SHA-1.
+#[derive(Debug, Clone, Default)]
+pub struct PadMaps {
+    default: PadMap,
+    roms: BTreeMap<String, PadMap>,
+    /// Where edited mappings are saved, if anywhere.
+    path: Option<PathBuf>,
+    user: PadFile,
+}
+
+impl PadMaps {
+    /// `chipsters/gamepad.toml` in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chipsters").join("gamepad.toml"))
+    }
+
+    /// The built-in mappings alone.
+    pub fn built_in() -> Result<Self> {
+        let mut padmaps = Self::default();
+        padmaps.add(&Self::parse_file(BUILT_IN)?)?;
+
+        Ok(padmaps)
+    }
+
+    /// The built-in mappings with those in the file at `path`, which need not exist, on top.
+    /// Edits are saved back to `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut padmaps = Self::built_in()?;
+        if path.exists() {
+            let text = fs::read_to_string(path).map_err(|err| {
+                anyhow!("Error reading gamepad mappings {}: {}", path.display(), err)
+            })?;
+            padmaps.user = Self::parse_file(&text)?;
+            padmaps.add(&padmaps.user.clone())?;
+        }
+        padmaps.path = Some(path.to_path_buf());
+
+        Ok(padmaps)
+    }
+
+    /// The mapping for ROMs without one of their own, and for the menu.
+    pub fn default_map(&self) -> PadMap {
+        self.default.clone()
+    }
+
+    /// The mapping for a ROM, by its hash, then its file name, then the default.
+    pub fn rom(&self, name: &str, rom: &[u8]) -> PadMap {
+        let file_name = name.rsplit('/').next().unwrap_or(name);
+        self.roms
+            .get(&rom_hash(rom))
+            .or_else(|| self.roms.get(file_name))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+
+    /// Replaces the mapping for the ROM with file name `name`, saving it to the user's file.
+    pub fn set(&mut self, name: &str, padmap: PadMap) -> Result<()> {
+        let file_name = name.rsplit('/').next().unwrap_or(name).to_string();
+        self.user.roms.insert(file_name.clone(), padmap.table());
+        self.roms.insert(file_name, padmap);
+
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let text = toml::to_string(&self.user)?;
+            fs::write(path, text).map_err(|err| {
+                anyhow!("Error saving gamepad mappings {}: {}", path.display(), err)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_file(text: &str) -> Result<PadFile> {
+        toml::from_str(text).map_err(|err| anyhow!("Invalid gamepad mappings: {err}"))
+    }
+
+    fn add(&mut self, file: &PadFile) -> Result<()> {
+        if !file.default.is_empty() {
+            self.default = PadMap::parse(&file.default)?;
+        }
+        for (rom, table) in &file.roms {
+            self.roms.insert(rom.clone(), PadMap::parse(table)?);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in() {
+        let padmaps = PadMaps::built_in().unwrap();
+
+        let pong = padmaps.rom("PONG", &[0x12, 0x00]);
+        assert_eq!(Some(1), pong.key(Button::Up));
+        assert_eq!(Some(4), pong.key(Button::Down));
+        assert_eq!(None, pong.key(Button::Left));
+
+        let tetris = padmaps.rom("Pack.zip/TETRIS", &[0x12, 0x00]);
+        assert_eq!(vec![Button::South], tetris.buttons(4));
+        assert_eq!(Some(5), tetris.key(Button::Left));
+        assert_eq!(Some(6), tetris.key(Button::Right));
+
+        let other = padmaps.rom("UNKNOWN", &[0x12, 0x00]);
+        assert_eq!(Some(2), other.key(Button::Up));
+        let keys = other.pressed([Button::Up, Button::South]);
+        assert!(keys[2] && keys[5]);
+    }
+
+    #[test]
+    fn test_save() {
+        let path = std::env::temp_dir().join(format!("chipsters-pad-{}.toml", std::process::id()));
+        let mut padmaps = PadMaps::load(&path).unwrap();
+
+        let mut padmap = PadMap::default();
+        padmap.bind(Button::North, 0xE);
+        padmaps.set("BRIX", padmap.clone()).unwrap();
+
+        let padmaps = PadMaps::load(&path).unwrap();
+        assert_eq!(padmap, padmaps.rom("BRIX", &[0x12, 0x00]));
+        assert_eq!(Some(1), padmaps.rom("PONG", &[0x12, 0x00]).key(Button::Up));
+
+        fs::remove_file(&path).unwrap();
+        assert!(PadMap::parse(&BTreeMap::from([("up".into(), "G".into())])).is_err());
+        assert!(PadMap::parse(&BTreeMap::from([("start".into(), "1".into())])).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Colours the display is drawn in.
+
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Colours by pixel value, as RGB. Value 0 is the background and 1 the foreground; 4- and
+/// 16-colour palettes also colour pixels lit on several XO-CHIP planes, with one bit per plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    colors: [[u8; 3]; 16],
+    len: usize,
+}
+
+impl Palette {
+    pub const CLASSIC: Self = Self::two([0xFF, 0xFF, 0xFF], [0x00, 0x00, 0x00]);
+    pub const AMBER: Self = Self::two([0xFF, 0xB0, 0x00], [0x1A, 0x0F, 0x00]);
+    pub const GREEN: Self = Self::two([0x33, 0xFF, 0x33], [0x00, 0x1A, 0x00]);
+    /// Octo's defaults: background, first plane, second plane and both planes.
+    pub const OCTO: Self = Self::of(&[
+        [0x99, 0x66, 0x00],
+        [0xFF, 0xCC, 0x00],
+        [0xFF, 0x66, 0x00],
+        [0x66, 0x22, 0x00],
+    ]);
+
+    /// Palettes that can be picked by name, in the order the palette hotkey steps through them.
+    pub const NAMED: [(&'static str, Self); 4] = [
+        ("classic", Self::CLASSIC),
+        ("amber", Self::AMBER),
+        ("green", Self::GREEN),
+        ("octo", Self::OCTO),
+    ];
+
+    const fn two(foreground: [u8; 3], background: [u8; 3]) -> Self {
+        Self::of(&[background, foreground])
+    }
+
+    /// Copies up to 16 colours, by pixel value.
+    const fn of(colors: &[[u8; 3]]) -> Self {
+        let mut palette = Self {
+            colors: [[0; 3]; 16],
+            len: colors.len(),
+        };
+        let mut i = 0;
+        while i < colors.len() {
+            palette.colors[i] = colors[i];
+            i += 1;
+        }
+        palette
+    }
+
+    /// A palette of 2, 4 or 16 colours by pixel value.
+    pub fn new(colors: &[[u8; 3]]) -> Result<Self> {
+        if ![2, 4, 16].contains(&colors.len()) {
+            return Err(anyhow!(
+                "A palette has 2, 4 or 16 colours, not {}",
+                colors.len()
+            ));
+        }
+
+        Ok(Self::of(colors))
+    }
+
+    pub fn colors(&self) -> &[[u8; 3]] {
+        &self.colors[..self.len]
+    }
+
+    pub fn background(&self) -> [u8; 3] {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> [u8; 3] {
+        self.colors[1]
+    }
+
+    /// The colour of a pixel value. Values past the end of the palette take its last colour, so a
+    /// 2-colour palette shows every lit plane in the foreground colour.
+    pub fn color(&self, value: u8) -> [u8; 3] {
+        self.colors[usize::from(value).min(self.len - 1)]
+    }
+
+    /// The named palette after this one, wrapping around, or the first for other palettes.
+    #[must_use]
+    pub fn next(self) -> Self {
+        let position = Self::NAMED.iter().position(|(_, named)| *named == self);
+        position.map_or(Self::NAMED[0].1, |i| {
+            Self::NAMED[(i + 1) % Self::NAMED.len()].1
+        })
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+fn parse_color(hex: &str) -> Result<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    let value = (digits.len() == 6)
+        .then(|| u32::from_str_radix(digits, 16).ok())
+        .flatten()
+        .ok_or_else(|| anyhow!("Invalid colour {hex:?}, expected RRGGBB"))?;
+
+    Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+impl FromStr for Palette {
+    type Err = anyhow::Error;
+
+    /// Parses a palette name, a foreground and background colour as `RRGGBB,RRGGBB`, or 4 or 16
+    /// colours by pixel value starting with the background.
+    fn from_str(palette: &str) -> Result<Self> {
+        if let Some((_, named)) = Self::NAMED
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(palette))
+        {
+            return Ok(*named);
+        }
+
+        if !palette.contains(',') {
+            return Err(anyhow!(
+                "Unknown palette {palette:?}, expected classic, amber, green, octo or RRGGBB,RRGGBB"
+            ));
+        }
+        let mut colors = palette
+            .split(',')
+            .map(parse_color)
+            .collect::<Result<Vec<_>>>()?;
+        if colors.len() == 2 {
+            colors.swap(0, 1);
+        }
+
+        Self::new(&colors)
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((name, _)) = Self::NAMED.iter().find(|(_, named)| named == self) {
+            return f.write_str(name);
+        }
+
+        let mut colors = self.colors().to_vec();
+        if colors.len() == 2 {
+            colors.swap(0, 1);
+        }
+        let hex: Vec<String> = colors
+            .iter()
+            .map(|[r, g, b]| format!("{r:02x}{g:02x}{b:02x}"))
+            .collect();
+        f.write_str(&hex.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Palette::AMBER, "Amber".parse().unwrap());
+
+        let palette: Palette = "#102030,000000".parse().unwrap();
+        assert_eq!([0x10, 0x20, 0x30], palette.foreground());
+        assert_eq!("102030,000000", palette.to_string());
+
+        let palette: Palette = "000000,ff0000,00ff00,0000ff".parse().unwrap();
+        assert_eq!([0xFF, 0x00, 0x00], palette.foreground());
+        assert_eq!([0x00, 0x00, 0xFF], palette.color(3));
+        assert_eq!([0x00, 0x00, 0xFF], palette.color(9));
+        assert_eq!("000000,ff0000,00ff00,0000ff", palette.to_string());
+        assert_eq!(
+            Palette::OCTO,
+            "996600,ffcc00,ff6600,662200".parse().unwrap()
+        );
+
+        assert!("purple".parse::<Palette>().is_err());
+        assert!("12345,000000".parse::<Palette>().is_err());
+        assert!("000000,111111,222222".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn test_next() {
+        assert_eq!(Palette::AMBER, Palette::CLASSIC.next());
+        assert_eq!(Palette::CLASSIC, Palette::OCTO.next());
+        assert_eq!(
+            Palette::CLASSIC,
+            "102030,000000".parse::<Palette>().unwrap().next()
+        );
+        assert_eq!([0xFF, 0xFF, 0xFF], Palette::CLASSIC.color(3));
+    }
+}
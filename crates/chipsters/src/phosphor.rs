@@ -0,0 +1,113 @@
+//! Phosphor persistence, which fades pixels out over a few frames instead of turning them off at
+//! once, hiding most of the flicker of sprites that are erased and redrawn with XOR.
+
+use crate::Palette;
+
+/// The brightness of each pixel, carried from frame to frame.
+#[derive(Debug, Clone, Default)]
+pub struct Phosphor {
+    /// How much of its brightness an unlit pixel keeps each frame, from 0.0 for none to just under
+    /// 1.0.
+    decay: f32,
+    brightness: Vec<f32>,
+    /// The last value each pixel was lit with, which it fades from.
+    values: Vec<u8>,
+}
+
+impl Phosphor {
+    /// The most brightness an unlit pixel can keep, so that every pixel goes dark eventually.
+    pub const MAX_DECAY: f32 = 0.95;
+
+    pub fn new(decay: f32) -> Self {
+        Self {
+            decay: decay.clamp(0.0, Self::MAX_DECAY),
+            ..Default::default()
+        }
+    }
+
+    pub fn decay(&self) -> f32 {
+        self.decay
+    }
+
+    pub fn set_decay(&mut self, decay: f32) {
+        self.decay = decay.clamp(0.0, Self::MAX_DECAY);
+    }
+
+    /// Number of pixels tracked, 0 before the first `update`.
+    pub fn len(&self) -> usize {
+        self.brightness.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.brightness.is_empty()
+    }
+
+    /// Lights the pixels lit in `framebuffer`, one byte per pixel as given by
+    /// `Chip8::framebuffer`, and fades the rest. A change of resolution starts afresh.
+    pub fn update(&mut self, framebuffer: &[u8]) {
+        if self.brightness.len() != framebuffer.len() {
+            self.brightness = vec![0.0; framebuffer.len()];
+            self.values = vec![0; framebuffer.len()];
+        }
+
+        for ((&value, brightness), last) in framebuffer
+            .iter()
+            .zip(&mut self.brightness)
+            .zip(&mut self.values)
+        {
+            if value == 0 {
+                *brightness *= self.decay;
+                // Below one step of 8-bit colour the pixel is dark.
+                if *brightness < 1.0 / 255.0 {
+                    *brightness = 0.0;
+                }
+            } else {
+                *brightness = 1.0;
+                *last = value;
+            }
+        }
+    }
+
+    /// The colour of each pixel since the last `update`, fading from its lit colour towards the
+    /// background.
+    pub fn colors<'a>(&'a self, palette: &'a Palette) -> impl Iterator<Item = [u8; 3]> + 'a {
+        let background = palette.background();
+        self.brightness
+            .iter()
+            .zip(&self.values)
+            .map(move |(&brightness, &value)| {
+                let lit = palette.color(value);
+                [0, 1, 2].map(|i| {
+                    let (from, to) = (f32::from(background[i]), f32::from(lit[i]));
+                    (from + (to - from) * brightness).round() as u8
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade() {
+        let mut phosphor = Phosphor::new(0.5);
+        phosphor.update(&[1, 0]);
+        phosphor.update(&[0, 1]);
+
+        let colors: Vec<_> = phosphor.colors(&Palette::CLASSIC).collect();
+        assert_eq!(vec![[128; 3], [255; 3]], colors);
+
+        for _ in 0..8 {
+            phosphor.update(&[0, 0]);
+        }
+        let colors: Vec<_> = phosphor.colors(&Palette::CLASSIC).collect();
+        assert_eq!(vec![[0; 3], [0; 3]], colors);
+
+        // Without decay pixels go dark at once.
+        let mut phosphor = Phosphor::new(0.0);
+        phosphor.update(&[1]);
+        phosphor.update(&[0]);
+        assert_eq!(Some([0; 3]), phosphor.colors(&Palette::CLASSIC).next());
+    }
+}
@@ -0,0 +1,566 @@
+//! Turns display colours into an RGBA image, with optional pixel-art upscaling and scanlines, and
+//! fits it into a window. Used by the window and by headless screenshots.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// An RGBA image, row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Frame {
+    /// An opaque image from colours row by row, such as those of `Phosphor::colors`.
+    pub fn new(width: usize, height: usize, colors: impl IntoIterator<Item = [u8; 3]>) -> Self {
+        let pixels: Vec<[u8; 4]> = colors
+            .into_iter()
+            .map(|[r, g, b]| [r, g, b, 0xFF])
+            .collect();
+        assert_eq!(width * height, pixels.len(), "colours for every pixel");
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.pixels.as_flattened()
+    }
+
+    /// The pixel at `x`, `y`, taking the nearest edge pixel outside the image.
+    fn pixel(&self, x: isize, y: isize) -> [u8; 4] {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// Builds an image `factor` times larger, filling each source pixel's block with `block`,
+    /// which gets the pixel's neighbourhood and the position in the block.
+    fn expand(&self, factor: usize, block: impl Fn(&Neighbours, usize, usize) -> [u8; 4]) -> Self {
+        let (width, height) = (self.width * factor, self.height * factor);
+        let mut pixels = vec![[0; 4]; width * height];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let neighbours = Neighbours::new(self, x as isize, y as isize);
+                for by in 0..factor {
+                    for bx in 0..factor {
+                        pixels[(y * factor + by) * width + x * factor + bx] =
+                            block(&neighbours, bx, by);
+                    }
+                }
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Turns and mirrors the image.
+    #[must_use]
+    pub fn orient(&self, orientation: Orientation) -> Self {
+        let (width, height) = orientation.size(self.width, self.height);
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (x, y) = orientation.source(x, y, self.width, self.height);
+                pixels.push(self.pixels[y * self.width + x]);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Scales up by a whole factor with square pixels.
+    #[must_use]
+    pub fn scale(&self, factor: usize) -> Self {
+        self.expand(factor, |n, _, _| n.at(0, 0))
+    }
+
+    /// Doubles the size, with every second row at half brightness.
+    #[must_use]
+    pub fn scanlines(&self) -> Self {
+        self.expand(2, |n, _, by| {
+            let [r, g, b, a] = n.at(0, 0);
+            if by == 0 {
+                [r, g, b, a]
+            } else {
+                [r / 2, g / 2, b / 2, a]
+            }
+        })
+    }
+}
+
+/// The 3×3 neighbourhood of a source pixel.
+struct Neighbours([[u8; 4]; 9]);
+
+impl Neighbours {
+    fn new(frame: &Frame, x: isize, y: isize) -> Self {
+        let mut pixels = [[0; 4]; 9];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = frame.pixel(x + i as isize % 3 - 1, y + i as isize / 3 - 1);
+        }
+        Self(pixels)
+    }
+
+    /// The pixel `dx`, `dy` away, each -1, 0 or 1.
+    fn at(&self, dx: isize, dy: isize) -> [u8; 4] {
+        self.0[((dy + 1) * 3 + dx + 1) as usize]
+    }
+}
+
+/// A software upscaler for pixel art.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    #[default]
+    None,
+    /// Scale2x (EPX), which doubles the size and rounds off diagonal steps.
+    Scale2x,
+    /// Scale3x, the same at three times the size.
+    Scale3x,
+    /// A light take on xBR at twice the size, which blends the corners of diagonal edges.
+    XbrLite,
+}
+
+impl Filter {
+    pub const NAMES: [(&'static str, Self); 4] = [
+        ("none", Self::None),
+        ("scale2x", Self::Scale2x),
+        ("scale3x", Self::Scale3x),
+        ("xbr-lite", Self::XbrLite),
+    ];
+
+    #[must_use]
+    pub fn apply(self, frame: &Frame) -> Frame {
+        match self {
+            Self::None => frame.clone(),
+            Self::Scale2x => frame.expand(2, scale2x),
+            Self::Scale3x => frame.expand(3, scale3x),
+            Self::XbrLite => frame.expand(2, xbr_lite),
+        }
+    }
+}
+
+fn scale2x(n: &Neighbours, bx: usize, by: usize) -> [u8; 4] {
+    // Towards the corner, the pixels beside and above or below it, and the ones opposite those.
+    let (sx, sy) = (bx as isize * 2 - 1, by as isize * 2 - 1);
+    let (side, vertical) = (n.at(sx, 0), n.at(0, sy));
+    let (other_side, other_vertical) = (n.at(-sx, 0), n.at(0, -sy));
+
+    if side == vertical && side != other_side && vertical != other_vertical {
+        side
+    } else {
+        n.at(0, 0)
+    }
+}
+
+fn scale3x(n: &Neighbours, bx: usize, by: usize) -> [u8; 4] {
+    let [a, b, c, d, e, f, g, h, i] = n.0;
+    let corner = |p: [u8; 4], q: [u8; 4], p2: [u8; 4], q2: [u8; 4]| p == q && q != p2 && p != q2;
+
+    match (bx, by) {
+        (0, 0) if corner(d, b, f, h) => d,
+        (1, 0) if (corner(d, b, f, h) && e != c) || (corner(f, b, d, h) && e != a) => b,
+        (2, 0) if corner(f, b, d, h) => f,
+        (0, 1) if (corner(d, b, f, h) && e != g) || (corner(d, h, f, b) && e != a) => d,
+        (2, 1) if (corner(f, b, d, h) && e != i) || (corner(f, h, d, b) && e != c) => f,
+        (0, 2) if corner(d, h, f, b) => d,
+        (1, 2) if (corner(d, h, f, b) && e != i) || (corner(f, h, d, b) && e != g) => h,
+        (2, 2) if corner(f, h, d, b) => f,
+        _ => e,
+    }
+}
+
+fn xbr_lite(n: &Neighbours, bx: usize, by: usize) -> [u8; 4] {
+    let (sx, sy) = (bx as isize * 2 - 1, by as isize * 2 - 1);
+    let e = n.at(0, 0);
+    // Named as if the corner were the bottom right: F beside it, H below it and I diagonal.
+    let (f, h, i) = (n.at(sx, 0), n.at(0, sy), n.at(sx, sy));
+    let (b, d) = (n.at(0, -sy), n.at(-sx, 0));
+    let (c, g) = (n.at(sx, -sy), n.at(-sx, sy));
+
+    // An edge runs between F and H when they are more alike than E and I.
+    let along = distance(e, c) + distance(e, g) + 4 * distance(h, f);
+    let across = distance(h, d) + distance(f, b) + 4 * distance(e, i);
+    if along >= across {
+        return e;
+    }
+
+    let nearer = if distance(e, f) <= distance(e, h) {
+        f
+    } else {
+        h
+    };
+    [0, 1, 2, 3].map(|channel| ((u16::from(e[channel]) + u16::from(nearer[channel])) / 2) as u8)
+}
+
+fn distance(p: [u8; 4], q: [u8; 4]) -> u32 {
+    p.iter()
+        .zip(q)
+        .map(|(&p, q)| u32::from(p.abs_diff(q)))
+        .sum()
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = Self::NAMES
+            .iter()
+            .find(|(_, filter)| filter == self)
+            .ok_or(fmt::Error)?;
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(filter, _)| filter.eq_ignore_ascii_case(name))
+            .map(|&(_, filter)| filter)
+            .ok_or_else(|| {
+                anyhow!("Unknown filter {name:?}, expected none, scale2x, scale3x or xbr-lite")
+            })
+    }
+}
+
+/// A clockwise turn of the display, for ROMs made to be played on their side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    /// The next quarter turn clockwise.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::R0 => Self::R90,
+            Self::R90 => Self::R180,
+            Self::R180 => Self::R270,
+            Self::R270 => Self::R0,
+        }
+    }
+
+    /// Whether width and height swap.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Self::R90 | Self::R270)
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = anyhow::Error;
+
+    fn try_from(degrees: u16) -> Result<Self> {
+        match degrees {
+            0 => Ok(Self::R0),
+            90 => Ok(Self::R90),
+            180 => Ok(Self::R180),
+            270 => Ok(Self::R270),
+            _ => Err(anyhow!(
+                "Invalid rotation {degrees}, expected 0, 90, 180 or 270"
+            )),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::R0 => 0,
+            Rotation::R90 => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        }
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°", u16::from(*self))
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    fn from_str(degrees: &str) -> Result<Self> {
+        let degrees: u16 = degrees
+            .trim_end_matches('°')
+            .parse()
+            .map_err(|_| anyhow!("Invalid rotation {degrees:?}, expected 0, 90, 180 or 270"))?;
+        degrees.try_into()
+    }
+}
+
+/// How the display is turned and then mirrored, as in Octo's rotation option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    /// Mirrors left and right.
+    pub flip_horizontal: bool,
+    /// Mirrors top and bottom.
+    pub flip_vertical: bool,
+}
+
+impl Orientation {
+    /// The size of a `width` by `height` image once oriented.
+    pub fn size<T>(self, width: T, height: T) -> (T, T) {
+        if self.rotation.is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// The pixel of a `width` by `height` source image shown at `x`, `y` once oriented.
+    pub fn source<T>(self, x: T, y: T, width: T, height: T) -> (T, T)
+    where
+        T: Copy + std::ops::Sub<Output = T> + From<u8>,
+    {
+        let (out_width, out_height) = self.size(width, height);
+        let one = T::from(1);
+        let x = if self.flip_horizontal {
+            out_width - one - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical {
+            out_height - one - y
+        } else {
+            y
+        };
+
+        match self.rotation {
+            Rotation::R0 => (x, y),
+            Rotation::R90 => (y, height - one - x),
+            Rotation::R180 => (width - one - x, height - one - y),
+            Rotation::R270 => (width - one - y, x),
+        }
+    }
+
+    /// The next combination of flips: none, horizontal, vertical, then both.
+    #[must_use]
+    pub fn next_flip(self) -> Self {
+        let (flip_horizontal, flip_vertical) = match (self.flip_horizontal, self.flip_vertical) {
+            (false, false) => (true, false),
+            (true, false) => (false, true),
+            (false, true) => (true, true),
+            (true, true) => (false, false),
+        };
+
+        Self {
+            flip_horizontal,
+            flip_vertical,
+            ..self
+        }
+    }
+}
+
+/// How the image is fitted into the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+    /// The largest whole multiple of the display size, with borders around it.
+    Integer,
+    /// As large as fits with square pixels, with borders on two sides.
+    #[default]
+    Aspect,
+    /// The whole window, whatever its shape.
+    Stretch,
+}
+
+impl Fit {
+    pub const NAMES: [(&'static str, Self); 3] = [
+        ("integer", Self::Integer),
+        ("aspect", Self::Aspect),
+        ("stretch", Self::Stretch),
+    ];
+
+    /// Where to draw a display of `size` pixels in a window of `window` pixels, as x, y, width
+    /// and height.
+    pub fn viewport(self, size: (f32, f32), window: (f32, f32)) -> [f32; 4] {
+        let scale = (window.0 / size.0).min(window.1 / size.1);
+        let scale = match self {
+            Self::Integer => scale.floor().max(1.0),
+            Self::Aspect => scale,
+            Self::Stretch => return [0.0, 0.0, window.0, window.1],
+        };
+        let (width, height) = (size.0 * scale, size.1 * scale);
+
+        [
+            ((window.0 - width) / 2.0).floor(),
+            ((window.1 - height) / 2.0).floor(),
+            width,
+            height,
+        ]
+    }
+}
+
+impl FromStr for Fit {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(fit, _)| fit.eq_ignore_ascii_case(name))
+            .map(|&(_, fit)| fit)
+            .ok_or_else(|| anyhow!("Unknown fit {name:?}, expected integer, aspect or stretch"))
+    }
+}
+
+/// How the display is drawn, beyond its colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderOptions {
+    pub filter: Filter,
+    pub scanlines: bool,
+    pub fit: Fit,
+}
+
+impl RenderOptions {
+    /// Applies the filter and then the scanlines.
+    #[must_use]
+    pub fn render(&self, frame: &Frame) -> Frame {
+        let frame = self.filter.apply(frame);
+        if self.scanlines {
+            frame.scanlines()
+        } else {
+            frame
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const O: [u8; 3] = [0; 3];
+    const X: [u8; 3] = [0xFF; 3];
+
+    /// A diagonal step: lit on the top left and bottom right.
+    fn step() -> Frame {
+        Frame::new(2, 2, [X, O, O, X])
+    }
+
+    fn lit(frame: &Frame) -> Vec<bool> {
+        frame.pixels.iter().map(|pixel| pixel[0] == 0xFF).collect()
+    }
+
+    #[test]
+    fn test_scale2x() {
+        let frame = Filter::Scale2x.apply(&step());
+        assert_eq!((4, 4), (frame.width, frame.height));
+        #[rustfmt::skip]
+        assert_eq!(
+            vec![
+                true, true, false, false,
+                true, false, true, false,
+                false, true, false, true,
+                false, false, true, true,
+            ],
+            lit(&frame)
+        );
+
+        // A lone pixel stays square.
+        let dot = Frame::new(3, 3, [O, O, O, O, X, O, O, O, O]);
+        assert_eq!(
+            Filter::None.apply(&dot).scale(2),
+            Filter::Scale2x.apply(&dot)
+        );
+    }
+
+    #[test]
+    fn test_scale3x_and_xbr() {
+        let frame = Filter::Scale3x.apply(&step());
+        assert_eq!((6, 6), (frame.width, frame.height));
+        assert!(lit(&frame)[3 * 6 + 2]);
+
+        // Beside a diagonal line, the corners towards it are blended half way.
+        let line = Frame::new(3, 3, [X, O, O, O, X, O, O, O, X]);
+        let frame = Filter::XbrLite.apply(&line);
+        assert_eq!([0x7F, 0x7F, 0x7F, 0xFF], frame.pixels[6 + 2]);
+        assert_eq!([0xFF; 4], frame.pixels[0]);
+        assert_eq!([0, 0, 0, 0xFF], frame.pixels[6 + 3]);
+    }
+
+    #[test]
+    fn test_scanlines() {
+        let options = RenderOptions {
+            scanlines: true,
+            ..Default::default()
+        };
+        let frame = options.render(&Frame::new(1, 1, [X]));
+
+        let dim = [0x7F, 0x7F, 0x7F, 0xFF];
+        assert_eq!(vec![[0xFF; 4], [0xFF; 4], dim, dim], frame.pixels);
+        assert_eq!(16, frame.as_bytes().len());
+    }
+
+    #[test]
+    fn test_orient() {
+        // 1 2 3
+        // 4 5 6
+        let frame = Frame::new(3, 2, (1..=6).map(|i| [i; 3]));
+        let values = |orientation: Orientation| -> Vec<u8> {
+            let frame = frame.orient(orientation);
+            frame.pixels.iter().map(|pixel| pixel[0]).collect()
+        };
+        let rotated = |degrees: u16| Orientation {
+            rotation: degrees.try_into().unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(vec![4, 1, 5, 2, 6, 3], values(rotated(90)));
+        assert_eq!(vec![6, 5, 4, 3, 2, 1], values(rotated(180)));
+        assert_eq!(vec![3, 6, 2, 5, 1, 4], values(rotated(270)));
+        assert_eq!((2, 3), rotated(270).size(3, 2));
+
+        let mirrored = Orientation::default().next_flip();
+        assert_eq!(vec![3, 2, 1, 6, 5, 4], values(mirrored));
+        let turned = Orientation {
+            flip_vertical: true,
+            ..rotated(90)
+        };
+        assert_eq!(vec![6, 3, 5, 2, 4, 1], values(turned));
+
+        assert_eq!(Rotation::R90, "90".parse().unwrap());
+        assert!("45".parse::<Rotation>().is_err());
+    }
+
+    #[test]
+    fn test_fit() {
+        let window = (1000.0, 600.0);
+        assert_eq!(
+            [20.0, 60.0, 960.0, 480.0],
+            Fit::Integer.viewport((64.0, 32.0), window)
+        );
+        assert_eq!(
+            [0.0, 50.0, 1000.0, 500.0],
+            Fit::Aspect.viewport((64.0, 32.0), window)
+        );
+        assert_eq!(
+            [0.0, 0.0, 1000.0, 600.0],
+            Fit::Stretch.viewport((64.0, 32.0), window)
+        );
+        assert!("zoom".parse::<Fit>().is_err());
+        assert_eq!(Filter::XbrLite, "XBR-lite".parse().unwrap());
+    }
+}
@@ -0,0 +1,149 @@
+//! The ROMs offered in the menu: files in a directory and the files inside `.zip` ROM packs.
+
+use anyhow::{anyhow, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chip8::{RomError, MAX_ROM_SIZE};
+use log::error;
+use zip::ZipArchive;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    File(PathBuf),
+    Zip { archive: PathBuf, entry: String },
+}
+
+/// A ROM in the menu, titled by its file name, or by the pack and its path inside the pack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rom {
+    pub title: String,
+    source: Source,
+}
+
+impl Rom {
+    pub fn read(&self) -> Result<Vec<u8>> {
+        match &self.source {
+            Source::File(path) => read(File::open(path)?),
+            Source::Zip { archive, entry } => {
+                read(ZipArchive::new(File::open(archive)?)?.by_name(entry)?)
+            }
+        }
+    }
+}
+
+/// Reads a ROM, giving up a byte past the largest ROM that fits in memory so that a huge file or
+/// zip entry is not read whole.
+pub fn read(reader: impl Read) -> Result<Vec<u8>> {
+    let mut rom = Vec::new();
+    reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut rom)?;
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(RomError::TooLarge(rom.len()).into());
+    }
+
+    Ok(rom)
+}
+
+pub fn is_zip(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+/// Lists the ROMs in a directory or a single `.zip` pack, sorted by title. Packs in a directory
+/// that cannot be read are left out.
+pub fn list(path: &Path) -> Result<Vec<Rom>> {
+    let mut roms = Vec::new();
+
+    if is_zip(path) {
+        roms.extend(list_zip(path, "")?);
+    } else {
+        let entries = path
+            .read_dir()
+            .map_err(|err| anyhow!("Error reading directory: {}", err))?;
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            if is_zip(&path) {
+                match list_zip(&path, &format!("{name}/")) {
+                    Ok(pack) => roms.extend(pack),
+                    Err(err) => error!("Skipping {}: {}", path.display(), err),
+                }
+            } else if path.is_file() {
+                roms.push(Rom {
+                    title: name,
+                    source: Source::File(path),
+                });
+            }
+        }
+    }
+
+    roms.sort_by(|a, b| a.title.cmp(&b.title));
+    Ok(roms)
+}
+
+fn list_zip(path: &Path, prefix: &str) -> Result<Vec<Rom>> {
+    let mut archive = ZipArchive::new(File::open(path)?)
+        .map_err(|err| anyhow!("Error reading ROM pack {}: {}", path.display(), err))?;
+
+    let mut roms = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        if file.is_file() {
+            roms.push(Rom {
+                title: format!("{prefix}{}", file.name()),
+                source: Source::Zip {
+                    archive: path.to_path_buf(),
+                    entry: file.name().to_string(),
+                },
+            });
+        }
+    }
+
+    Ok(roms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    fn test_list() {
+        let dir = std::env::temp_dir().join(format!("chipsters-roms-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("folder")).unwrap();
+        std::fs::write(dir.join("PONG"), [0x12, 0x00]).unwrap();
+
+        let mut pack = ZipWriter::new(File::create(dir.join("Pack.ZIP")).unwrap());
+        pack.add_directory("games/", SimpleFileOptions::default())
+            .unwrap();
+        pack.start_file("games/BRIX", SimpleFileOptions::default())
+            .unwrap();
+        pack.write_all(&[0x60, 0x01]).unwrap();
+        pack.start_file("HUGE", SimpleFileOptions::default())
+            .unwrap();
+        pack.write_all(&vec![0; 1 << 20]).unwrap();
+        pack.finish().unwrap();
+        std::fs::write(dir.join("Broken.zip"), b"not a zip").unwrap();
+
+        let roms = list(&dir).unwrap();
+        let titles: Vec<_> = roms.iter().map(|rom| rom.title.as_str()).collect();
+        assert_eq!(vec!["PONG", "Pack.ZIP/HUGE", "Pack.ZIP/games/BRIX"], titles);
+        assert_eq!(vec![0x12, 0x00], roms[0].read().unwrap());
+        assert!(roms[1].read().is_err());
+        assert_eq!(vec![0x60, 0x01], roms[2].read().unwrap());
+
+        assert!(list(&dir.join("Broken.zip")).is_err());
+        let roms = list(&dir.join("Pack.ZIP")).unwrap();
+        assert_eq!("games/BRIX", roms[1].title);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
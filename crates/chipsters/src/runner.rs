@@ -0,0 +1,804 @@
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+
+use chip8::{Chip8, State};
+use log::error;
+
+use crate::config::{Config, SavedSettings, Settings};
+use crate::frontend::{AudioSink, Clock, Command, Input, InputSource, Speed, VideoSink, View};
+use crate::keymap::Keymap;
+use crate::padmap::{PadMap, PadMaps};
+use crate::render::Orientation;
+use crate::roms::{self, Rom};
+use crate::Palette;
+
+/// Whether the frontend should keep running after a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Where the binding screen is up to: which key of `Runner::BIND_ORDER` it asks for, whether
+/// that key's old host keys or buttons have been replaced yet, and which of the two it binds.
+#[derive(Debug, Clone, Copy)]
+struct Binding {
+    step: usize,
+    replaced: bool,
+    gamepad: bool,
+}
+
+/// Drives the emulator, the ROM menu and pausing independently of how frames are shown, input is
+/// read, sound is played and time is kept.
+#[derive(Debug)]
+pub struct Runner<V, I, A, C> {
+    pub chip8: Chip8,
+    pub video: V,
+    pub input: I,
+    pub audio: A,
+    pub clock: C,
+    /// The speed `Command::ToggleTurbo` switches to.
+    pub turbo: Speed,
+    config: Config,
+    overrides: Settings,
+    saved: SavedSettings,
+    palette: Palette,
+    orientation: Orientation,
+    keymap: Keymap,
+    padmaps: PadMaps,
+    padmap: PadMap,
+    /// File name of the running ROM, which gamepad mappings are saved under.
+    rom_name: Option<String>,
+    binding: Option<Binding>,
+    speed: Speed,
+    slow_frames: u32,
+    advance: bool,
+    roms: Vec<Rom>,
+    rom_titles: Option<Vec<String>>,
+    rom_cursor: usize,
+}
+
+impl<V, I, A, C> Runner<V, I, A, C>
+where
+    V: VideoSink,
+    I: InputSource,
+    A: AudioSink,
+    C: Clock,
+{
+    /// CHIP-8 keys in the order the binding screen asks for them, row by row on the keypad.
+    const BIND_ORDER: [usize; 16] = [
+        0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+    ];
+
+    pub fn new(video: V, input: I, audio: A, clock: C) -> Self {
+        Self {
+            chip8: Chip8::default(),
+            video,
+            input,
+            audio,
+            clock,
+            turbo: Speed::Uncapped,
+            config: Config::default(),
+            overrides: Settings::default(),
+            saved: SavedSettings::default(),
+            palette: Palette::default(),
+            orientation: Orientation::default(),
+            keymap: Keymap::default(),
+            padmaps: PadMaps::default(),
+            padmap: PadMap::default(),
+            rom_name: None,
+            binding: None,
+            speed: Speed::Normal,
+            slow_frames: 0,
+            advance: false,
+            roms: Vec::new(),
+            rom_titles: None,
+            rom_cursor: 0,
+        }
+    }
+
+    /// Uses `config` for every ROM loaded from now on, with `overrides` such as command line
+    /// options taking precedence.
+    pub fn configure(&mut self, config: Config, overrides: Settings) -> Result<()> {
+        self.config = config;
+        self.overrides = overrides;
+        self.apply_defaults()
+    }
+
+    /// Uses `saved` for the settings changed from inside chipsters, such as the palette, and
+    /// saves changes to it.
+    pub fn set_saved(&mut self, saved: SavedSettings) {
+        self.saved = saved;
+    }
+
+    /// Uses `padmaps` for the gamepad buttons of every ROM loaded from now on.
+    pub fn set_padmaps(&mut self, padmaps: PadMaps) {
+        self.padmaps = padmaps;
+        self.padmap = self.padmaps.default_map();
+        self.input.set_padmap(self.padmap.clone());
+    }
+
+    /// Loads a ROM file, or lists the ROMs in a directory or `.zip` pack in the menu.
+    pub fn load(&mut self, rom_path: &Path) -> Result<()> {
+        if !rom_path.exists() {
+            return Err(anyhow!("ROM path does not exist: {}", rom_path.display()));
+        }
+
+        if rom_path.is_dir() || roms::is_zip(rom_path) {
+            self.roms = roms::list(rom_path)?;
+            self.rom_titles = Some(self.roms.iter().map(|rom| rom.title.clone()).collect());
+        } else {
+            let name = rom_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            fs::File::open(rom_path)
+                .map_err(anyhow::Error::from)
+                .and_then(roms::read)
+                .and_then(|rom| self.start(&name, &rom))
+                .map_err(|err| {
+                    anyhow!("Error loading ROM at path {}: {}", rom_path.display(), err)
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles one frame of input, runs the emulator for a frame and presents the result.
+    pub fn frame(&mut self) -> Result<Flow> {
+        let input = self.input.poll();
+
+        if self.binding.is_some() {
+            self.bind(&input);
+        } else if self.play(input)? == Flow::Quit {
+            return Ok(Flow::Quit);
+        }
+
+        self.audio
+            .set_beeping(self.binding.is_none() && self.chip8.sound_active());
+
+        let view = match (self.binding, self.chip8.state) {
+            (Some(binding), _) if binding.gamepad => View::PadBinding {
+                key: Self::BIND_ORDER[binding.step],
+                padmap: &self.padmap,
+            },
+            (Some(binding), _) => View::Binding {
+                key: Self::BIND_ORDER[binding.step],
+                keymap: &self.keymap,
+            },
+            (None, State::Paused) => View::Paused(&self.chip8),
+            (None, State::Off) => View::Menu {
+                titles: self.rom_titles.as_deref().unwrap_or_default(),
+                cursor: self.rom_cursor,
+            },
+            _ => View::Game(&self.chip8),
+        };
+        self.video.present(view);
+        self.clock.wait_frame();
+
+        Ok(Flow::Continue)
+    }
+
+    /// Handles commands and CHIP-8 keys, and runs the emulator.
+    fn play(&mut self, input: Input) -> Result<Flow> {
+        for command in input.commands {
+            if self.handle_command(command)? == Flow::Quit {
+                return Ok(Flow::Quit);
+            }
+        }
+
+        self.chip8.reset_keys();
+        for (i, _) in input.keys.iter().enumerate().filter(|(_, &down)| down) {
+            self.chip8.key_down(i);
+        }
+
+        let ran = if std::mem::take(&mut self.advance) {
+            self.chip8.state = State::Running;
+            self.chip8.run_frame()?;
+            if self.chip8.state == State::Running {
+                self.chip8.state = State::Paused;
+            }
+            1
+        } else {
+            self.run_frames()?
+        };
+        if ran > 0 {
+            self.video.frames_ran(&self.chip8);
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    /// Binds the host keys typed, or the gamepad buttons pressed, to the CHIP-8 key being asked
+    /// for. Select moves on to the next key and Back leaves. New key bindings are kept for every
+    /// ROM loaded afterwards, and new button bindings are saved for the running ROM. Bindings that
+    /// cannot be saved still apply for this session.
+    fn bind(&mut self, input: &Input) {
+        let Some(mut binding) = self.binding else {
+            return;
+        };
+
+        let key = Self::BIND_ORDER[binding.step];
+        if binding.gamepad {
+            for &button in &input.buttons {
+                if !binding.replaced {
+                    self.padmap.clear(key);
+                    binding.replaced = true;
+                }
+                self.padmap.bind(button, key);
+            }
+            if !input.buttons.is_empty() {
+                self.input.set_padmap(self.padmap.clone());
+            }
+        } else {
+            for &host in &input.typed {
+                if !binding.replaced {
+                    self.keymap.clear(key);
+                    binding.replaced = true;
+                }
+                self.keymap.bind(key, host);
+            }
+            if !input.typed.is_empty() {
+                self.overrides
+                    .keys
+                    .insert(format!("{key:X}"), self.keymap.hosts(key).iter().collect());
+                self.input.set_keymap(self.keymap.clone());
+            }
+        }
+
+        let mut done = false;
+        for command in &input.commands {
+            match command {
+                Command::Select => {
+                    binding.step += 1;
+                    binding.replaced = false;
+                    done = binding.step == Self::BIND_ORDER.len();
+                }
+                Command::Back => done = true,
+                _ => {}
+            }
+            if done {
+                break;
+            }
+        }
+        self.binding = (!done).then_some(binding);
+
+        if let Some(name) = &self.rom_name {
+            if done && binding.gamepad {
+                if let Err(err) = self.padmaps.set(name, self.padmap.clone()) {
+                    error!("Error saving gamepad buttons for {name}: {err}");
+                }
+            }
+        }
+    }
+
+    /// Applies the settings for the ROM and starts it.
+    fn start(&mut self, name: &str, rom: &[u8]) -> Result<()> {
+        let mut settings = self.config.saved_rom_settings(&self.saved, name, rom);
+        settings.merge(&self.overrides);
+        self.apply(&settings)?;
+        self.padmap = self.padmaps.rom(name, rom);
+        self.input.set_padmap(self.padmap.clone());
+        self.rom_name = Some(name.to_string());
+        self.chip8.load_rom_bytes(rom)?;
+
+        Ok(())
+    }
+
+    /// Applies the settings shared by every ROM, for the menu.
+    fn apply_defaults(&mut self) -> Result<()> {
+        let mut settings = self.config.settings.clone();
+        settings.merge(&self.overrides);
+        self.padmap = self.padmaps.default_map();
+        self.input.set_padmap(self.padmap.clone());
+        self.rom_name = None;
+        self.apply(&settings)
+    }
+
+    fn apply(&mut self, settings: &Settings) -> Result<()> {
+        self.chip8.platform = settings.platform()?;
+        self.turbo = settings.turbo.unwrap_or(Speed::Uncapped);
+        self.palette = settings.palette.unwrap_or_default();
+        self.video.set_palette(self.palette);
+        self.video.set_phosphor(settings.phosphor.unwrap_or(0.0));
+        self.orientation = settings.orientation();
+        self.video.set_orientation(self.orientation);
+        self.keymap = settings.keymap()?;
+        self.input.set_keymap(self.keymap.clone());
+        self.audio.set_volume(settings.volume.unwrap_or(1.0));
+
+        Ok(())
+    }
+
+    /// Saves a setting changed from inside chipsters for the running ROM, if any. A setting that
+    /// cannot be saved still applies for this session.
+    fn remember(&mut self, change: impl FnOnce(&mut Settings)) {
+        if let Some(name) = &self.rom_name {
+            if let Err(err) = self.saved.update(name, change) {
+                error!("Error saving settings for {name}: {err}");
+            }
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.slow_frames = 0;
+        self.video.set_speed(speed);
+    }
+
+    /// Runs the frames due this host frame at the current speed, returning how many ran. A
+    /// finished program keeps its last picture on screen until it is reset or left, while its
+    /// timers run down.
+    fn run_frames(&mut self) -> Result<u32> {
+        let frames = match self.speed {
+            Speed::Slow(n) => {
+                self.slow_frames = (self.slow_frames + 1) % n.max(1);
+                u32::from(self.slow_frames == 0)
+            }
+            Speed::Normal => 1,
+            Speed::Turbo(n) => n,
+            Speed::Uncapped => u32::MAX,
+        };
+
+        if matches!(self.chip8.state, State::Paused | State::Off) {
+            return Ok(0);
+        }
+
+        let mut ran = 0;
+        while ran < frames {
+            self.chip8.run_frame()?;
+            ran += 1;
+            if self.speed == Speed::Uncapped
+                && (self.chip8.state != State::Running || self.clock.frame_over())
+            {
+                break;
+            }
+        }
+
+        Ok(ran)
+    }
+
+    fn handle_command(&mut self, command: Command) -> Result<Flow> {
+        match command {
+            Command::Back => {
+                if self.chip8.state == State::Off || self.rom_titles.is_none() {
+                    return Ok(Flow::Quit);
+                }
+
+                self.chip8.reset()?;
+                self.chip8.state = State::Off;
+                self.apply_defaults()?;
+            }
+            Command::Quit => return Ok(Flow::Quit),
+            Command::TogglePause => {
+                self.chip8.state = match self.chip8.state {
+                    State::Running => State::Paused,
+                    State::Paused => State::Running,
+                    _ => self.chip8.state,
+                }
+            }
+            Command::Reset => self.chip8.reset()?,
+            Command::Faster => self.set_speed(self.speed.faster()),
+            Command::Slower => self.set_speed(self.speed.slower()),
+            Command::ToggleTurbo => self.set_speed(if self.speed == Speed::Normal {
+                self.turbo
+            } else {
+                Speed::Normal
+            }),
+            Command::FrameAdvance => self.advance = self.chip8.state == State::Paused,
+            Command::NextPalette => {
+                self.palette = self.palette.next();
+                self.video.set_palette(self.palette);
+                let palette = self.palette;
+                self.remember(|settings| settings.palette = Some(palette));
+            }
+            Command::Rotate | Command::Flip => {
+                self.orientation = if command == Command::Rotate {
+                    Orientation {
+                        rotation: self.orientation.rotation.next(),
+                        ..self.orientation
+                    }
+                } else {
+                    self.orientation.next_flip()
+                };
+                self.video.set_orientation(self.orientation);
+                let orientation = self.orientation;
+                self.remember(|settings| {
+                    settings.rotation = Some(orientation.rotation);
+                    settings.flip_horizontal = Some(orientation.flip_horizontal);
+                    settings.flip_vertical = Some(orientation.flip_vertical);
+                });
+            }
+            Command::Bind => {
+                self.binding = Some(Binding {
+                    step: 0,
+                    replaced: false,
+                    gamepad: false,
+                });
+            }
+            // Button bindings belong to a ROM, so there are none to edit from the menu.
+            Command::BindPad if self.rom_name.is_some() => {
+                self.binding = Some(Binding {
+                    step: 0,
+                    replaced: false,
+                    gamepad: true,
+                });
+            }
+            _ if self.chip8.state == State::Off => self.handle_menu(command)?,
+            _ => {}
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    fn handle_menu(&mut self, command: Command) -> Result<()> {
+        let Some(rom_titles) = &self.rom_titles else {
+            return Ok(());
+        };
+        if rom_titles.is_empty() {
+            return Ok(());
+        }
+
+        match command {
+            Command::Up => {
+                self.rom_cursor = (self.rom_cursor + rom_titles.len() - 1) % rom_titles.len();
+            }
+            Command::Down => {
+                self.rom_cursor = (self.rom_cursor + rom_titles.len() + 1) % rom_titles.len();
+            }
+            Command::Left => {
+                self.rom_cursor = if self.rom_cursor as i32 - 10 <= 0 {
+                    rom_titles.len() - 1
+                } else {
+                    self.rom_cursor - 10
+                }
+            }
+            Command::Right => {
+                self.rom_cursor = if self.rom_cursor + 10 >= rom_titles.len() - 1 {
+                    0
+                } else {
+                    self.rom_cursor + 10
+                }
+            }
+            Command::Select => {
+                let rom = self.roms[self.rom_cursor].clone();
+                // A ROM that cannot be loaded leaves the menu up for another pick.
+                if let Err(err) = rom.read().and_then(|bytes| self.start(&rom.title, &bytes)) {
+                    error!("Error loading ROM {}: {}", rom.title, err);
+                    self.apply_defaults()?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{Input, MemoryAudio, MemoryClock, MemoryInput, MemoryVideo, Screen};
+    use crate::padmap::Button;
+    use crate::render::Rotation;
+    use chip8::Target;
+
+    type TestRunner = Runner<MemoryVideo, MemoryInput, MemoryAudio, MemoryClock>;
+
+    fn runner(rom_path: &str) -> TestRunner {
+        let mut runner = Runner::new(
+            MemoryVideo::default(),
+            MemoryInput::default(),
+            MemoryAudio::default(),
+            MemoryClock::default(),
+        );
+        runner
+            .load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(rom_path))
+            .unwrap();
+        runner
+    }
+
+    fn press(runner: &mut TestRunner, commands: &[Command]) -> Flow {
+        runner.input.push(Input {
+            commands: commands.to_vec(),
+            ..Default::default()
+        });
+        runner.frame().unwrap()
+    }
+
+    #[test]
+    fn test_menu() {
+        let mut runner = runner("../../roms");
+
+        press(&mut runner, &[]);
+        assert_eq!(Some(Screen::Menu { cursor: 0 }), runner.video.screen);
+
+        press(&mut runner, &[Command::Up]);
+        assert_eq!(Some(Screen::Menu { cursor: 22 }), runner.video.screen);
+
+        press(&mut runner, &[Command::Down, Command::Down, Command::Right]);
+        assert_eq!(Some(Screen::Menu { cursor: 11 }), runner.video.screen);
+
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(State::Running, runner.chip8.state);
+        assert!(matches!(runner.video.screen, Some(Screen::Game(_))));
+
+        assert_eq!(Flow::Continue, press(&mut runner, &[Command::Back]));
+        assert_eq!(State::Off, runner.chip8.state);
+        assert_eq!(Flow::Quit, press(&mut runner, &[Command::Back]));
+        assert_eq!(5, runner.clock.frames);
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut runner = runner("../../roms/PONG");
+
+        press(&mut runner, &[Command::TogglePause]);
+        assert_eq!(Some(Screen::Paused), runner.video.screen);
+
+        for _ in 0..10 {
+            press(&mut runner, &[]);
+        }
+        assert_eq!(Some(Screen::Paused), runner.video.screen);
+
+        press(&mut runner, &[Command::TogglePause]);
+        assert_eq!(State::Running, runner.chip8.state);
+        let Some(Screen::Game(pixels)) = &runner.video.screen else {
+            panic!("expected the game screen");
+        };
+        assert!(pixels.iter().any(|&pixel| pixel));
+
+        assert_eq!(Flow::Quit, press(&mut runner, &[Command::Back]));
+    }
+
+    #[test]
+    fn test_rom_settings() {
+        let mut runner = runner("../../roms");
+        let config: Config = "turbo = \"2x\"\n[roms.PONG]\ntarget = \"super-chip\""
+            .parse()
+            .unwrap();
+        let overrides = Settings {
+            ips: Some(600),
+            ..Default::default()
+        };
+        runner.configure(config, overrides).unwrap();
+        assert_eq!(Target::CosmacVIP, runner.chip8.platform.target);
+        assert_eq!(Speed::Turbo(2), runner.turbo);
+
+        runner.rom_cursor = runner
+            .roms
+            .iter()
+            .position(|rom| rom.title == "PONG")
+            .unwrap();
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(Target::SuperChip, runner.chip8.platform.target);
+        assert_eq!(10, runner.chip8.platform.tick_rate);
+
+        press(&mut runner, &[Command::Back]);
+        assert_eq!(Target::CosmacVIP, runner.chip8.platform.target);
+    }
+
+    #[test]
+    fn test_menu_load_error() {
+        let dir = std::env::temp_dir().join(format!("chipsters-runner-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("EMPTY"), []).unwrap();
+        fs::write(dir.join("PONG"), [0x12, 0x00]).unwrap();
+        let mut runner = runner(dir.to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(Flow::Continue, press(&mut runner, &[Command::Select]));
+        assert_eq!(State::Off, runner.chip8.state);
+        assert_eq!(Some(Screen::Menu { cursor: 0 }), runner.video.screen);
+        assert!(runner.rom_name.is_none());
+    }
+
+    #[test]
+    fn test_next_palette() {
+        let mut runner = runner("../../roms");
+        let config: Config = "palette = \"green\"".parse().unwrap();
+        runner.configure(config, Settings::default()).unwrap();
+        assert_eq!(Palette::GREEN, runner.video.palette);
+
+        runner.rom_cursor = runner
+            .roms
+            .iter()
+            .position(|rom| rom.title == "PONG")
+            .unwrap();
+        press(&mut runner, &[Command::Select, Command::NextPalette]);
+        assert_eq!(Palette::OCTO, runner.video.palette);
+        assert_eq!(
+            Some(Palette::OCTO),
+            runner
+                .saved
+                .rom("PONG")
+                .and_then(|settings| settings.palette)
+        );
+
+        // Other ROMs keep the configured palette, and PONG gets its own back.
+        press(&mut runner, &[Command::Back]);
+        assert_eq!(Palette::GREEN, runner.video.palette);
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(Palette::OCTO, runner.video.palette);
+
+        // A palette that cannot be saved still applies.
+        let file = std::env::temp_dir().join(format!("chipsters-file-{}", std::process::id()));
+        fs::write(&file, "").unwrap();
+        runner.set_saved(SavedSettings::load(&file.join("saved.toml")).unwrap());
+        assert_eq!(Flow::Continue, press(&mut runner, &[Command::NextPalette]));
+        fs::remove_file(&file).unwrap();
+        assert_eq!(Palette::CLASSIC, runner.video.palette);
+    }
+
+    #[test]
+    fn test_orientation() {
+        let mut runner = runner("../../roms");
+        let config: Config = "[roms.VBRIX]\nrotation = 90".parse().unwrap();
+        runner.configure(config, Settings::default()).unwrap();
+
+        runner.rom_cursor = runner
+            .roms
+            .iter()
+            .position(|rom| rom.title == "VBRIX")
+            .unwrap();
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(Rotation::R90, runner.video.orientation.rotation);
+
+        press(&mut runner, &[Command::Rotate, Command::Flip]);
+        let orientation = runner.video.orientation;
+        assert_eq!(Rotation::R180, orientation.rotation);
+        assert!(orientation.flip_horizontal && !orientation.flip_vertical);
+        let saved = runner.saved.rom("VBRIX").unwrap();
+        assert_eq!(Some(Rotation::R180), saved.rotation);
+        assert_eq!(Some(true), saved.flip_horizontal);
+
+        // The menu is drawn upright.
+        press(&mut runner, &[Command::Back]);
+        assert_eq!(Orientation::default(), runner.video.orientation);
+    }
+
+    #[test]
+    fn test_binding() {
+        let mut runner = runner("../../roms/PONG");
+
+        press(&mut runner, &[Command::Bind]);
+        assert_eq!(
+            Some(Screen::Binding {
+                key: 1,
+                hosts: vec!['1']
+            }),
+            runner.video.screen
+        );
+
+        // Two players share key 1, then key 2 is left alone and key 3 is bound.
+        for input in [
+            Input {
+                typed: vec!['u', 'j'],
+                ..Default::default()
+            },
+            Input {
+                commands: vec![Command::Select, Command::Select],
+                ..Default::default()
+            },
+            Input {
+                typed: vec!['k'],
+                commands: vec![Command::Back],
+                ..Default::default()
+            },
+        ] {
+            runner.input.push(input);
+            runner.frame().unwrap();
+        }
+
+        assert_eq!(&['u', 'j'], runner.keymap.hosts(1));
+        assert_eq!(&['2'], runner.keymap.hosts(2));
+        assert_eq!(&['k'], runner.keymap.hosts(3));
+        assert!(matches!(runner.video.screen, Some(Screen::Game(_))));
+
+        // The bindings outlive loading a ROM.
+        runner
+            .load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../../roms/PONG"))
+            .unwrap();
+        assert_eq!(&['u', 'j'], runner.keymap.hosts(1));
+    }
+
+    #[test]
+    fn test_pad_binding() {
+        let mut runner = runner("../../roms");
+        runner.set_padmaps(PadMaps::built_in().unwrap());
+        press(&mut runner, &[Command::BindPad]);
+        assert_eq!(Some(Screen::Menu { cursor: 0 }), runner.video.screen);
+
+        runner.rom_cursor = runner
+            .roms
+            .iter()
+            .position(|rom| rom.title == "PONG")
+            .unwrap();
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(Some(1), runner.padmap.key(Button::Up));
+
+        press(&mut runner, &[Command::BindPad]);
+        assert_eq!(
+            Some(Screen::PadBinding {
+                key: 1,
+                buttons: vec![Button::Up]
+            }),
+            runner.video.screen
+        );
+        runner.input.push(Input {
+            buttons: vec![Button::LeftBumper],
+            commands: vec![Command::Back],
+            ..Default::default()
+        });
+        runner.frame().unwrap();
+
+        assert_eq!(Some(1), runner.padmap.key(Button::LeftBumper));
+        assert_eq!(None, runner.padmap.key(Button::Up));
+        assert_eq!(Some(4), runner.padmap.key(Button::Down));
+
+        // Back in the menu the default mapping applies, and PONG gets its new one again.
+        press(&mut runner, &[Command::Back]);
+        assert_eq!(Some(2), runner.padmap.key(Button::Up));
+        press(&mut runner, &[Command::Select]);
+        assert_eq!(Some(1), runner.padmap.key(Button::LeftBumper));
+    }
+
+    #[test]
+    fn test_speed() {
+        let mut runner = runner("../../roms/PONG");
+        // Counts its loops in V0, a whole number of them each frame.
+        runner.chip8.platform.tick_rate = 16;
+        runner
+            .chip8
+            .load_rom_bytes(&[0x70, 0x01, 0x12, 0x00])
+            .unwrap();
+        let loops = |runner: &TestRunner| runner.chip8.registers().v[0];
+
+        press(&mut runner, &[]);
+        let per_frame = loops(&runner);
+
+        press(&mut runner, &[Command::Faster]);
+        assert_eq!(Speed::Turbo(2), runner.video.speed);
+        assert_eq!(3 * per_frame, loops(&runner));
+
+        press(&mut runner, &[Command::Slower, Command::Slower]);
+        assert_eq!(Speed::Slow(2), runner.video.speed);
+        assert_eq!(3 * per_frame, loops(&runner));
+        press(&mut runner, &[]);
+        assert_eq!(4 * per_frame, loops(&runner));
+
+        press(&mut runner, &[Command::ToggleTurbo]);
+        assert_eq!(Speed::Normal, runner.speed());
+        press(&mut runner, &[Command::ToggleTurbo]);
+        assert_eq!(Speed::Uncapped, runner.speed());
+        assert_eq!(6 * per_frame, loops(&runner));
+    }
+
+    #[test]
+    fn test_frame_advance() {
+        let mut runner = runner("../../roms/PONG");
+        runner.chip8.platform.tick_rate = 16;
+        runner
+            .chip8
+            .load_rom_bytes(&[0x70, 0x01, 0x12, 0x00])
+            .unwrap();
+
+        press(&mut runner, &[Command::TogglePause]);
+        press(&mut runner, &[Command::FrameAdvance]);
+        let per_frame = runner.chip8.registers().v[0];
+        assert!(per_frame > 0);
+        assert_eq!(State::Paused, runner.chip8.state);
+
+        let emulated = runner.video.emulated;
+        press(&mut runner, &[]);
+        assert_eq!(emulated, runner.video.emulated);
+        press(&mut runner, &[Command::FrameAdvance]);
+        assert_eq!(2 * per_frame, runner.chip8.registers().v[0]);
+        assert_eq!(emulated + 1, runner.video.emulated);
+    }
+}